@@ -6,7 +6,7 @@
 use std::io::Write;
 use std::io::{self, Read};
 
-use byteorder::{LittleEndian, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use bytes::{Buf, BytesMut};
 use flate2::Compression;
 #[cfg(not(feature = "libdeflate"))]
@@ -15,7 +15,10 @@ use flate2::{Compress, Decompress, FlushCompress};
 #[cfg(not(feature = "libdeflate"))]
 use crate::check::Check;
 use crate::deflate::Bgzf;
-use crate::{BlockFormatSpec, FooterValues, GzpError, BUFSIZE};
+use crate::mgzip::{
+    header_crc16, skip_optional_fields, GzHeader, GzHeaderOptions, FCOMMENT, FHCRC, FNAME,
+};
+use crate::{BlockFormatSpec, FooterValues, FormatSpec, GzpError, BUFSIZE};
 
 pub(crate) const BGZF_BLOCK_SIZE: usize = 65280;
 // default from bgzf, compress(BGZF_BLOCK_SIZE) < BGZF_MAX_BLOCK_SIZE
@@ -38,11 +41,55 @@ pub(crate) static BGZF_EOF: &[u8] = &[
 ];
 #[cfg(feature = "libdeflate")]
 pub(crate) const BGZF_HEADER_SIZE: usize = 18;
-#[cfg(feature = "libdeflate")]
-pub(crate) const BGZF_FOOTER_SIZE: usize = 8;
 
 const EXTRA: f64 = 0.1;
 
+/// Walk the gzip EXTRA field of a BGZF member header looking for the `BC` subfield and return its
+/// `BSIZE` value (the total member length, header through footer, is `BSIZE + 1`).
+///
+/// `header` must start at the first byte of the member and contain at least its first
+/// [`BGZF_HEADER_SIZE`] bytes; subfields past that point (i.e. past `BC` itself, for a
+/// spec-conformant BGZF member) are out of reach since that's all a [`ParDecompress`]-style
+/// reader has buffered by the time it needs to know the block size.
+///
+/// [`ParDecompress`]: crate::par::decompress::ParDecompress
+pub(crate) fn find_bsize(header: &[u8]) -> Result<u16, GzpError> {
+    if header.len() < 12 {
+        return Err(GzpError::InvalidHeader("Header shorter than the fixed gzip prefix"));
+    }
+    let xlen = LittleEndian::read_u16(&header[10..12]) as usize;
+    let extra = &header[12..std::cmp::min(12 + xlen, header.len())];
+    let mut i = 0;
+    while i + 4 <= extra.len() {
+        let si1 = extra[i];
+        let si2 = extra[i + 1];
+        let slen = LittleEndian::read_u16(&extra[i + 2..i + 4]) as usize;
+        let payload_end = i + 4 + slen;
+        if payload_end > extra.len() {
+            break;
+        }
+        if si1 == b'B' && si2 == b'C' && slen == 2 {
+            return Ok(LittleEndian::read_u16(&extra[i + 4..i + 6]));
+        }
+        i = payload_end;
+    }
+    Err(GzpError::InvalidHeader("No BC subfield found in BGZF extra field"))
+}
+
+/// Number of leading bytes of a block's `remainder` (everything after the fixed
+/// [`BGZF_HEADER_SIZE`]-byte header, which already covers the mandatory `BC` subfield) occupied by
+/// user-supplied FEXTRA subfields (if `XLEN` exceeds the mandatory `BC` subfield's 6 bytes) plus
+/// any FNAME/FCOMMENT/FHCRC fields, i.e. everything that precedes the deflate payload. Used by
+/// [`crate::deflate::Bgzf`]'s [`BlockFormatSpec::header_extra_len`] impl so
+/// [`ParDecompress`](crate::par::decompress::ParDecompress) can skip straight to the payload.
+///
+/// `header` is the fixed [`BGZF_HEADER_SIZE`]-byte block header.
+pub(crate) fn header_extra_len(header: &[u8], remainder: &[u8]) -> usize {
+    let xlen = LittleEndian::read_u16(&header[10..12]) as usize;
+    let user_extra_len = xlen.saturating_sub(6).min(remainder.len());
+    user_extra_len + skip_optional_fields(header[3], &remainder[user_extra_len..])
+}
+
 #[inline]
 fn extra_amount(input_len: usize) -> usize {
     std::cmp::max(128, (input_len as f64 * EXTRA) as usize)
@@ -61,6 +108,8 @@ where
     decompressor: Decompress,
     reader: R,
     format: Bgzf,
+    /// The gzip header metadata parsed from the first block, if any has been read yet.
+    header: Option<GzHeader>,
 }
 
 impl<R> BgzfSyncReader<R>
@@ -79,7 +128,28 @@ where
             compressed_buffer: BytesMut::with_capacity(BGZF_BLOCK_SIZE),
             decompressor,
             reader,
-            format: Bgzf {},
+            format: Bgzf::new(),
+            header: None,
+        }
+    }
+
+    /// The gzip header metadata (filename, comment, mtime, OS) parsed from the first block, or
+    /// `None` if no block has been read yet.
+    pub fn header(&self) -> Option<&GzHeader> {
+        self.header.as_ref()
+    }
+
+    /// Read a NUL-terminated field (FNAME or FCOMMENT), returning its bytes (excluding the
+    /// terminator).
+    fn read_nul_terminated(&mut self) -> io::Result<Vec<u8>> {
+        let mut field = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            self.reader.read_exact(&mut byte)?;
+            if byte[0] == 0 {
+                return Ok(field);
+            }
+            field.push(byte[0]);
         }
     }
 }
@@ -104,6 +174,12 @@ where
     compressor: libdeflater::Compressor,
     #[cfg(not(feature = "libdeflate"))]
     compressor: Compress,
+    /// Gzip header metadata (filename, comment, mtime, OS, FHCRC) written into only the first
+    /// block, matching how standard BGZF-producing tools embed stream-level metadata in the
+    /// leading member.
+    header_options: GzHeaderOptions,
+    /// Whether the first block (the one carrying `header_options`) has been written yet.
+    wrote_header: bool,
     /// The inner writer
     writer: W,
 }
@@ -114,10 +190,20 @@ where
 {
     /// Create a new [`BgzfSyncWriter`]
     pub fn new(writer: W, compression_level: Compression) -> Self {
-        Self::with_capacity(writer, compression_level, BGZF_BLOCK_SIZE)
+        Self::with_capacity(
+            writer,
+            compression_level,
+            BGZF_BLOCK_SIZE,
+            GzHeaderOptions::default(),
+        )
     }
 
-    pub fn with_capacity(writer: W, compression_level: Compression, blocksize: usize) -> Self {
+    pub fn with_capacity(
+        writer: W,
+        compression_level: Compression,
+        blocksize: usize,
+        header_options: GzHeaderOptions,
+    ) -> Self {
         assert!(blocksize <= BGZF_BLOCK_SIZE);
         #[cfg(feature = "libdeflate")]
         let compressor = libdeflater::Compressor::new(
@@ -130,6 +216,8 @@ where
             blocksize,
             compression_level,
             compressor,
+            header_options,
+            wrote_header: false,
             writer,
         }
     }
@@ -145,7 +233,12 @@ pub fn decompress(
     footer_vals: FooterValues,
 ) -> Result<(), GzpError> {
     if footer_vals.amount != 0 {
-        let _bytes_decompressed = decoder.deflate_decompress(&input[..input.len() - 8], output)?;
+        let bytes_decompressed = decoder.deflate_decompress(&input[..input.len() - 8], output)?;
+        if bytes_decompressed != output.len() {
+            return Err(GzpError::InvalidBlockSize(
+                "Decompressed size does not match the footer's ISIZE",
+            ));
+        }
     }
     let mut new_check = libdeflater::Crc::new();
     new_check.update(output);
@@ -171,12 +264,19 @@ pub fn decompress(
     use flate2::Crc;
 
     if footer_vals.amount != 0 {
-        let _bytes_decompressed = decoder.decompress(
+        let before = decoder.total_out();
+        decoder.decompress(
             &input[..input.len() - 8],
             output,
             flate2::FlushDecompress::Finish,
         )?;
+        let bytes_decompressed = (decoder.total_out() - before) as usize;
         decoder.reset(false);
+        if bytes_decompressed != output.len() {
+            return Err(GzpError::InvalidBlockSize(
+                "Decompressed size does not match the footer's ISIZE",
+            ));
+        }
     }
     let mut new_check = flate2::Crc::new();
     new_check.update(output);
@@ -198,14 +298,24 @@ pub fn compress(
     encoder: &mut libdeflater::Compressor,
     compression_level: Compression,
 ) -> Result<Vec<u8>, GzpError> {
-    // The plus 64 allows odd small sized blocks to extend up to a byte boundary
-    // let mut buffer = Vec::with_capacity(input.len() + 64);
-    let mut buffer =
-        vec![0; BGZF_HEADER_SIZE + input.len() + extra_amount(input.len()) + BGZF_FOOTER_SIZE];
+    compress_with_header(input, encoder, compression_level, None)
+}
 
+/// Compress a block of bytes, adding a header (carrying `header_options`, if any) and footer.
+#[cfg(feature = "libdeflate")]
+#[inline]
+pub fn compress_with_header(
+    input: &[u8],
+    encoder: &mut libdeflater::Compressor,
+    compression_level: Compression,
+    header_options: Option<&GzHeaderOptions>,
+) -> Result<Vec<u8>, GzpError> {
+    let mut compressed = vec![0; input.len() + extra_amount(input.len())];
     let bytes_written = encoder
-        .deflate_compress(input, &mut buffer[BGZF_HEADER_SIZE..])
+        .deflate_compress(input, &mut compressed)
         .map_err(GzpError::LibDeflaterCompress)?;
+    compressed.truncate(bytes_written);
+
     // Make sure that compressed buffer is smaller than
     if bytes_written >= MAX_BGZF_BLOCK_SIZE {
         return Err(GzpError::BlockSizeExceeded(
@@ -217,11 +327,8 @@ pub fn compress(
     check.update(input);
 
     // Add header with total byte sizes
-    let header = header_inner(compression_level, bytes_written as u16);
-    buffer[0..BGZF_HEADER_SIZE].copy_from_slice(&header);
-    buffer.truncate(BGZF_HEADER_SIZE + bytes_written);
-
-    // let mut footer = Vec::with_capacity(8);
+    let mut buffer = header_inner(compression_level, bytes_written as u16, header_options);
+    buffer.extend(compressed);
     buffer.write_u32::<LittleEndian>(check.sum())?;
     buffer.write_u32::<LittleEndian>(input.len() as u32)?;
 
@@ -235,6 +342,18 @@ pub fn compress(
     input: &[u8],
     encoder: &mut Compress,
     compression_level: Compression,
+) -> Result<Vec<u8>, GzpError> {
+    compress_with_header(input, encoder, compression_level, None)
+}
+
+#[cfg(not(feature = "libdeflate"))]
+/// Compress a block of bytes, adding a header (carrying `header_options`, if any) and footer.
+#[inline]
+pub fn compress_with_header(
+    input: &[u8],
+    encoder: &mut Compress,
+    compression_level: Compression,
+    header_options: Option<&GzHeaderOptions>,
 ) -> Result<Vec<u8>, GzpError> {
     {
         // The plus 64 allows odd small sized blocks to extend up to a byte boundary
@@ -253,7 +372,7 @@ pub fn compress(
         check.update(input);
 
         // Add header with total byte sizes
-        let mut header = header_inner(compression_level, buffer.len() as u16);
+        let mut header = header_inner(compression_level, buffer.len() as u16, header_options);
         let footer = footer_inner(&check);
         header.extend(buffer.into_iter().chain(footer));
         encoder.reset();
@@ -261,36 +380,87 @@ pub fn compress(
     }
 }
 
-/// Create an Bgzf style header
+/// Create a Bgzf style header, including any optional FNAME/FCOMMENT/FHCRC fields requested by
+/// `header_options`.
 #[inline]
-fn header_inner(compression_level: Compression, compressed_size: u16) -> Vec<u8> {
+fn header_inner(
+    compression_level: Compression,
+    compressed_size: u16,
+    header_options: Option<&GzHeaderOptions>,
+) -> Vec<u8> {
     // Size = header + extra subfield size + filename with null terminator (if present) + datablock size (unknknown) + footer
     // const size: u32  = 16 + 4 + 0 + 0 + 8;
+    // FTEXT is ignored: it's a per-stream hint that doesn't make sense applied to a BGZF block
+    // header (see `Gzip::with_header_options`).
+    let (fname, comment, mtime, os, xfl, header_crc, _text, user_extra) = header_options
+        .map(GzHeaderOptions::as_parts)
+        .unwrap_or((None, None, 0, 255, None, false, false, &[]));
+
+    let comp_value = xfl.unwrap_or_else(|| {
+        if compression_level.level() >= Compression::best().level() {
+            2
+        } else if compression_level.level() <= Compression::fast().level() {
+            4
+        } else {
+            0
+        }
+    });
 
-    let comp_value = if compression_level.level() >= Compression::best().level() {
-        2
-    } else if compression_level.level() <= Compression::fast().level() {
-        4
-    } else {
-        0
-    };
+    let mut flags = 4; // FEXTRA is always set to carry the mandatory BC subfield
+    if fname.is_some() {
+        flags |= FNAME;
+    }
+    if comment.is_some() {
+        flags |= FCOMMENT;
+    }
+    if header_crc {
+        flags |= FHCRC;
+    }
 
     let mut header = Vec::with_capacity(20);
     header.write_u8(31).unwrap(); // magic byte
     header.write_u8(139).unwrap(); // magic byte
     header.write_u8(8).unwrap(); // compression method
-    header.write_u8(4).unwrap(); // name / comment / extraflag
-    header.write_u32::<LittleEndian>(0).unwrap(); // mtime
+    header.write_u8(flags).unwrap(); // name / comment / extraflag
+    header.write_u32::<LittleEndian>(mtime).unwrap(); // mtime
     header.write_u8(comp_value).unwrap(); // compression value
-    header.write_u8(255).unwrap(); // OS
-    header.write_u16::<LittleEndian>(6).unwrap(); // Extra flag len
+    header.write_u8(os).unwrap(); // OS
+    header
+        .write_u16::<LittleEndian>(6 + user_extra.len() as u16)
+        .unwrap(); // Extra flag len
     header.write_u8(b'B').unwrap(); // Bgzf subfield ID 1
     header.write_u8(b'C').unwrap(); // Bgzf subfield ID2
     header.write_u16::<LittleEndian>(2).unwrap(); // Bgzf sufield len
+
+    // Optional FNAME/FCOMMENT/FHCRC fields follow the mandatory BC subfield (and any
+    // user-supplied subfields), and their length must be folded into the BSIZE recorded there.
+    let mut optional = Vec::new();
+    if let Some(fname) = fname {
+        optional.extend_from_slice(fname);
+        optional.push(0);
+    }
+    if let Some(comment) = comment {
+        optional.extend_from_slice(comment);
+        optional.push(0);
+    }
+    let trailing_crc_len: u16 = if header_crc { 2 } else { 0 };
+
     header
-        .write_u16::<LittleEndian>(compressed_size + 26 - 1)
+        .write_u16::<LittleEndian>(
+            compressed_size + 26 - 1
+                + user_extra.len() as u16
+                + optional.len() as u16
+                + trailing_crc_len,
+        )
         .unwrap(); // Size of block including header and footer - 1 BLEN
 
+    header.extend_from_slice(user_extra);
+    header.extend(optional);
+    if header_crc {
+        let crc = header_crc16(&header);
+        header.write_u16::<LittleEndian>(crc).unwrap();
+    }
+
     header
 }
 
@@ -313,8 +483,15 @@ where
         self.buffer.extend_from_slice(buf);
         if self.buffer.len() >= self.blocksize {
             let b = self.buffer.split_to(self.blocksize).freeze();
-            let compressed = compress(&b[..], &mut self.compressor, self.compression_level)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let header_options = (!self.wrote_header).then_some(&self.header_options);
+            let compressed = compress_with_header(
+                &b[..],
+                &mut self.compressor,
+                self.compression_level,
+                header_options,
+            )
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.wrote_header = true;
             self.writer.write_all(&compressed)?;
         }
         Ok(buf.len())
@@ -327,8 +504,15 @@ where
                 .buffer
                 .split_to(std::cmp::min(self.buffer.len(), BGZF_BLOCK_SIZE))
                 .freeze();
-            let compressed = compress(&b[..], &mut self.compressor, self.compression_level)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let header_options = (!self.wrote_header).then_some(&self.header_options);
+            let compressed = compress_with_header(
+                &b[..],
+                &mut self.compressor,
+                self.compression_level,
+                header_options,
+            )
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.wrote_header = true;
             self.writer.write_all(&compressed)?;
             self.writer.write_all(BGZF_EOF)?; // this is an empty block
         }
@@ -368,11 +552,99 @@ where
             } else if total_read <= buf.len() {
                 let mut header_buf = vec![0; Bgzf::HEADER_SIZE];
                 if let Ok(()) = self.reader.read_exact(&mut header_buf) {
-                    self.format.check_header(&header_buf).unwrap();
-                    let size = self.format.get_block_size(&header_buf).unwrap();
+                    self.format
+                        .check_header(&header_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let size = self
+                        .format
+                        .get_block_size(&header_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+                    // The fixed BGZF_HEADER_SIZE-byte window above covers only the mandatory
+                    // gzip prefix and BC subfield; any user-supplied extra subfields (past BC,
+                    // see `GzHeaderOptions::extra_subfield`) and optional FNAME/FCOMMENT/FHCRC
+                    // fields (present only on the first block, see `BgzfSyncWriter`) follow it
+                    // and must be read separately so `size` and `consumed` stay in sync.
+                    let flags = header_buf[3];
+                    let mtime = LittleEndian::read_u32(&header_buf[4..8]);
+                    let os = header_buf[9];
+                    let xlen = LittleEndian::read_u16(&header_buf[10..12]) as usize;
+                    let mut consumed = Bgzf::HEADER_SIZE;
+                    let mut header_bytes = header_buf.clone();
+
+                    let mut extra_other = Vec::new();
+                    if xlen > 6 {
+                        let mut rest = vec![0u8; xlen - 6];
+                        self.reader.read_exact(&mut rest)?;
+                        header_bytes.extend_from_slice(&rest);
+                        consumed += rest.len();
+
+                        // Walk any subfields beyond the mandatory BC subfield already consumed
+                        // above, keeping their raw bytes around to expose via `GzHeader::extra`.
+                        let mut i = 0;
+                        while i + 4 <= rest.len() {
+                            let subfield_len = LittleEndian::read_u16(&rest[i + 2..i + 4]) as usize;
+                            let subfield_end = std::cmp::min(i + 4 + subfield_len, rest.len());
+                            extra_other.extend_from_slice(&rest[i..subfield_end]);
+                            i = subfield_end;
+                        }
+                    }
+
+                    let fname = if flags & FNAME != 0 {
+                        let field = self.read_nul_terminated()?;
+                        header_bytes.extend_from_slice(&field);
+                        header_bytes.push(0);
+                        consumed += field.len() + 1;
+                        Some(field)
+                    } else {
+                        None
+                    };
+                    let comment = if flags & FCOMMENT != 0 {
+                        let field = self.read_nul_terminated()?;
+                        header_bytes.extend_from_slice(&field);
+                        header_bytes.push(0);
+                        consumed += field.len() + 1;
+                        Some(field)
+                    } else {
+                        None
+                    };
+                    if flags & FHCRC != 0 {
+                        let mut crc_buf = [0u8; 2];
+                        self.reader.read_exact(&mut crc_buf)?;
+                        consumed += 2;
+                        let expected = LittleEndian::read_u16(&crc_buf);
+                        let found = header_crc16(&header_bytes);
+                        if found != expected {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                GzpError::InvalidCheck {
+                                    found: found as u32,
+                                    expected: expected as u32,
+                                },
+                            ));
+                        }
+                    }
+                    if self.header.is_none() {
+                        self.header = Some(GzHeader {
+                            fname,
+                            comment,
+                            mtime,
+                            os,
+                            extra: extra_other,
+                        });
+                    }
+
+                    let remaining = size.checked_sub(consumed).filter(|r| *r >= 8).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            GzpError::InvalidBlockSize(
+                                "BGZF block size is too small for its own header and footer",
+                            ),
+                        )
+                    })?;
 
                     self.compressed_buffer.clear();
-                    self.compressed_buffer.resize(size - Bgzf::HEADER_SIZE, 0);
+                    self.compressed_buffer.resize(remaining, 0);
                     self.reader.read_exact(&mut self.compressed_buffer)?;
 
                     let check = self.format.get_footer_values(&self.compressed_buffer);
@@ -385,7 +657,7 @@ where
                         &mut self.buffer,
                         check,
                     )
-                    .unwrap();
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                 } else {
                     break;
                 }
@@ -452,4 +724,135 @@ mod test {
         // Assert decompressed output is equal to input
         assert_eq!(input.to_vec(), bytes);
     }
+
+    #[test]
+    fn test_bgzfsync_with_header_options() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes, sized so it spans more than one block
+        let input = vec![b'x'; BGZF_BLOCK_SIZE + 100];
+
+        let header_options = GzHeaderOptions::new()
+            .fname("input.txt")
+            .comment("a test comment")
+            .mtime(12345)
+            .os(3)
+            .header_crc(true);
+
+        // Compress input to output
+        let mut bgzf = BgzfSyncWriter::with_capacity(
+            out_writer,
+            Compression::new(3),
+            BGZF_BLOCK_SIZE,
+            header_options,
+        );
+        bgzf.write_all(&input).unwrap();
+        bgzf.flush().unwrap();
+        drop(bgzf);
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut decoder = BgzfSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        decoder.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input, across both blocks
+        assert_eq!(input, bytes);
+
+        // Assert the first block's header metadata round-tripped
+        let header = decoder.header().unwrap();
+        assert_eq!(header.fname, Some(b"input.txt".to_vec()));
+        assert_eq!(header.comment, Some(b"a test comment".to_vec()));
+        assert_eq!(header.mtime, 12345);
+        assert_eq!(header.os, 3);
+    }
+
+    #[test]
+    fn test_bgzfsync_with_user_extra_subfield() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input = b"a short bgzf block".to_vec();
+
+        // A user-defined extra subfield, written after the mandatory BC subfield.
+        let header_options = GzHeaderOptions::new()
+            .fname("input.txt")
+            .extra_subfield(b'X', b'X', b"payload".to_vec());
+
+        let mut bgzf =
+            BgzfSyncWriter::with_capacity(out_writer, Compression::new(3), BGZF_BLOCK_SIZE, header_options);
+        bgzf.write_all(&input).unwrap();
+        bgzf.flush().unwrap();
+        drop(bgzf);
+
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        let mut decoder = BgzfSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        decoder.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+
+        // The user subfield survives round-trip in `GzHeader::extra`, alongside the other
+        // header metadata.
+        let header = decoder.header().unwrap();
+        assert_eq!(header.fname, Some(b"input.txt".to_vec()));
+        assert_eq!(header.extra, b"XX\x07\0payload".to_vec());
+    }
+
+    #[test]
+    fn test_bgzfsync_rejects_bad_header_crc() {
+        let mut out_buf = vec![];
+        let header_options = GzHeaderOptions::new().fname("input.txt").header_crc(true);
+        let mut bgzf =
+            BgzfSyncWriter::with_capacity(&mut out_buf, Compression::new(3), 16, header_options);
+        bgzf.write_all(b"hello, world").unwrap();
+        bgzf.flush().unwrap();
+        drop(bgzf);
+
+        // Corrupt a byte in the FNAME field covered by the FHCRC, which is written right after
+        // the fixed BGZF_HEADER_SIZE-byte prefix.
+        out_buf[Bgzf::HEADER_SIZE] ^= 0xff;
+
+        let mut decoder = BgzfSyncReader::new(&out_buf[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_bgzfsync_rejects_garbage_header() {
+        let garbage = [0x00u8; 32];
+        let mut decoder = BgzfSyncReader::new(&garbage[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_bgzfsync_rejects_truncated_block() {
+        let mut out_buf = vec![];
+        let mut bgzf = BgzfSyncWriter::new(&mut out_buf, Compression::new(3));
+        bgzf.write_all(b"hello, world").unwrap();
+        bgzf.flush().unwrap();
+        drop(bgzf);
+
+        // Chop off the tail of the trailing EOF block's footer so its declared BSIZE runs past
+        // the data actually available.
+        out_buf.truncate(out_buf.len() - 4);
+
+        let mut decoder = BgzfSyncReader::new(&out_buf[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
 }
@@ -26,8 +26,6 @@ use std::io::Write;
 
 use byteorder::{ByteOrder, LittleEndian};
 use bytes::Bytes;
-#[cfg(feature = "any_zlib")]
-use flate2::write::ZlibEncoder;
 use flate2::{
     write::{DeflateEncoder, GzEncoder},
     Compress, Compression, FlushCompress,
@@ -40,7 +38,13 @@ use crate::bgzf::{BgzfSyncWriter, BGZF_BLOCK_SIZE};
 use crate::check::Adler32;
 use crate::check::{Check, Crc32, PassThroughCheck};
 use crate::mgzip::MgzipSyncWriter;
+#[cfg(feature = "any_zlib")]
+use crate::par::compress::ParCompressBuilder;
 use crate::syncz::SyncZ;
+#[cfg(feature = "any_zlib")]
+use crate::syncz::SyncZBuilder;
+#[cfg(feature = "any_zlib")]
+use crate::zlib::ZlibSyncWriter;
 use crate::{bgzf, check, mgzip, BlockFormatSpec, FormatSpec, GzpError, Pair, SyncWriter, ZWriter};
 
 /// The extra amount of space to add to the compressed vec to allow for EOF and other possible extra characters
@@ -58,14 +62,59 @@ fn output_buffer_size(input_len: usize) -> usize {
 
 /// Gzip deflate stream with gzip header and footer.
 #[derive(Copy, Clone, Debug)]
-pub struct Gzip {}
+pub struct Gzip {
+    /// Gzip header metadata set via [`Gzip::with_header_options`], written into the stream's one
+    /// header.
+    ///
+    /// `fname`/`comment` are leaked for the life of the process so `Gzip` can stay `Copy`, as
+    /// required by [`FormatSpec`], mirroring [`Mgzip`]'s header metadata fields.
+    fname: Option<&'static [u8]>,
+    comment: Option<&'static [u8]>,
+    mtime: u32,
+    os: u8,
+    xfl: Option<u8>,
+    header_crc: bool,
+    /// Whether to set the FTEXT flag, hinting that the uncompressed data is ASCII text.
+    text: bool,
+    /// Pre-encoded user-defined FEXTRA subfields (see [`mgzip::GzHeaderOptions::extra_subfield`]),
+    /// leaked alongside `fname`/`comment` so `Gzip` can stay `Copy`.
+    extra: Option<&'static [u8]>,
+}
+
+impl Gzip {
+    /// Create a [`Gzip`] format bound to the given gzip header metadata (filename, comment,
+    /// mtime, OS, XFL, FHCRC, FTEXT, extra subfields), written into the stream's header -- the
+    /// parallel-writer equivalent of a plain `gzip -N` invocation.
+    pub fn with_header_options(options: mgzip::GzHeaderOptions) -> Self {
+        let (fname, comment, mtime, os, xfl, header_crc, text, extra) = options.into_parts();
+        Self {
+            fname: fname.map(|f| &*Box::leak(f.into_boxed_slice())),
+            comment: comment.map(|c| &*Box::leak(c.into_boxed_slice())),
+            mtime,
+            os,
+            xfl,
+            header_crc,
+            text,
+            extra: (!extra.is_empty()).then(|| &*Box::leak(extra.into_boxed_slice())),
+        }
+    }
+}
 
 impl FormatSpec for Gzip {
     type C = Crc32;
     type Compressor = Compress;
 
     fn new() -> Self {
-        Self {}
+        Self {
+            fname: None,
+            comment: None,
+            mtime: 0,
+            os: mgzip::OS_UNKNOWN,
+            xfl: None,
+            header_crc: false,
+            text: false,
+            extra: None,
+        }
     }
 
     #[inline]
@@ -89,6 +138,7 @@ impl FormatSpec for Gzip {
         encoder: &mut Self::Compressor,
         compression_level: Compression,
         dict: Option<&Bytes>,
+        _is_first: bool,
         is_last: bool,
     ) -> Result<Vec<u8>, GzpError> {
         // The plus 128 allows odd small sized blocks to extend up to a byte boundary and end stream
@@ -109,27 +159,63 @@ impl FormatSpec for Gzip {
         Ok(buffer)
     }
 
-    #[rustfmt::skip]
     fn header(&self, compression_level: Compression) -> Vec<u8> {
-        let comp_value = if compression_level.level() >= Compression::best().level() {
-            2
-        } else if compression_level.level() <= Compression::fast().level() {
-            4
-        } else {
-            0
-        };
+        let xfl = self.xfl.unwrap_or_else(|| {
+            if compression_level.level() >= Compression::best().level() {
+                2
+            } else if compression_level.level() <= Compression::fast().level() {
+                4
+            } else {
+                0
+            }
+        });
 
-        let header = vec![
-            Pair { num_bytes: 1, value: 31 }, // 0x1f in flate2
-            Pair { num_bytes: 1, value: 139 }, // 0x8b in flate2
-            Pair { num_bytes: 1, value: 8 }, // deflate
-            Pair { num_bytes: 1, value: 0 }, // name / comment
-            Pair { num_bytes: 4, value: 0 }, // mtime
-            Pair { num_bytes: 1, value: comp_value }, // Compression level
-            Pair { num_bytes: 1, value: 255 }, // OS
+        let mut flags = 0;
+        if self.text {
+            flags |= mgzip::FTEXT;
+        }
+        if self.extra.is_some() {
+            flags |= mgzip::FEXTRA;
+        }
+        if self.fname.is_some() {
+            flags |= mgzip::FNAME;
+        }
+        if self.comment.is_some() {
+            flags |= mgzip::FCOMMENT;
+        }
+        if self.header_crc {
+            flags |= mgzip::FHCRC;
+        }
+
+        let mut header = vec![
+            31,   // 0x1f in flate2
+            139,  // 0x8b in flate2
+            8,    // deflate
+            flags,
         ];
+        header.extend_from_slice(&self.mtime.to_le_bytes());
+        header.push(xfl);
+        header.push(self.os);
+
+        // Optional FEXTRA/FNAME/FCOMMENT follow the fixed 10-byte header, in RFC 1952 order, with
+        // FHCRC (covering everything written so far) last.
+        if let Some(extra) = self.extra {
+            header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+            header.extend_from_slice(extra);
+        }
+        if let Some(fname) = self.fname {
+            header.extend_from_slice(fname);
+            header.push(0);
+        }
+        if let Some(comment) = self.comment {
+            header.extend_from_slice(comment);
+            header.push(0);
+        }
+        if self.header_crc {
+            header.extend_from_slice(&mgzip::header_crc16(&header).to_le_bytes());
+        }
 
-        self.to_bytes(&header)
+        header
     }
 
     #[rustfmt::skip]
@@ -167,7 +253,32 @@ impl<W: Write> ZWriter for SyncZ<GzEncoder<W>> {
 /// Zlib deflate stream with zlib header and footer.
 #[cfg(feature = "any_zlib")]
 #[derive(Copy, Clone, Debug)]
-pub struct Zlib {}
+pub struct Zlib {
+    /// A preset dictionary installed on every compressor reset, also recorded in the FDICT bit
+    /// and Adler-32 trailer of the stream header per RFC 1950.
+    ///
+    /// Leaked for the life of the process so `Zlib` can stay `Copy`, as required by
+    /// [`FormatSpec`], mirroring [`crate::zstd::Zstd::with_dictionary`].
+    dictionary: Option<&'static [u8]>,
+}
+
+#[cfg(feature = "any_zlib")]
+impl Zlib {
+    /// Create a [`Zlib`] format bound to a preset dictionary, installed on every compressor
+    /// reset and recorded in the stream header so a decoder can tell one was used.
+    ///
+    /// Note that neither [`ZlibSyncReader`](crate::zlib::ZlibSyncReader) nor
+    /// [`ParZlibDecompress`](crate::par::decompress::ParZlibDecompress) can read the resulting
+    /// stream back: both reject a set FDICT bit outright, since RFC 1950 requires the exact same
+    /// dictionary bytes be supplied to decode, and neither has a way to receive one yet. Decoding
+    /// such a stream currently requires a raw [`flate2::Decompress`] with `set_dictionary`
+    /// called up front, as in this crate's own tests.
+    pub fn with_dictionary(dictionary: Vec<u8>) -> Self {
+        Self {
+            dictionary: Some(Box::leak(dictionary.into_boxed_slice())),
+        }
+    }
+}
 
 #[cfg(feature = "any_zlib")]
 impl FormatSpec for Zlib {
@@ -175,7 +286,7 @@ impl FormatSpec for Zlib {
     type Compressor = Compress;
 
     fn new() -> Self {
-        Self {}
+        Self { dictionary: None }
     }
 
     #[inline]
@@ -198,13 +309,14 @@ impl FormatSpec for Zlib {
         encoder: &mut Self::Compressor,
         _compression_level: Compression,
         dict: Option<&Bytes>,
+        _is_first: bool,
         is_last: bool,
     ) -> Result<Vec<u8>, GzpError> {
         // The plus 16 allows odd small sized blocks to extend up to a byte boundary and end stream
         let mut buffer = Vec::with_capacity(output_buffer_size(input.len()));
         #[cfg(feature = "any_zlib")]
-        if let Some(dict) = dict {
-            encoder.set_dictionary(&dict[..])?;
+        if let Some(dict) = dict.map(|d| &d[..]).or(self.dictionary) {
+            encoder.set_dictionary(dict)?;
         }
         encoder.compress_vec(
             input,
@@ -233,13 +345,24 @@ impl FormatSpec for Zlib {
 
         let mut head = (0x78 << 8) + // deflate, 32k window
             comp_value; // compression level clue
+        if self.dictionary.is_some() {
+            head += 1 << 5; // FDICT
+        }
         head += 31 - (head % 31); // make it multiple of 31
-        let header = vec![
+        let mut header = vec![
             Pair {
                 num_bytes: -2,
                 value: head,
             }, // zlib uses big-endian
         ];
+        if let Some(dictionary) = self.dictionary {
+            let mut check = Adler32::new();
+            check.update(dictionary);
+            header.push(Pair {
+                num_bytes: -4,
+                value: check.sum() as usize,
+            });
+        }
         self.to_bytes(&header)
     }
 
@@ -252,22 +375,64 @@ impl FormatSpec for Zlib {
     }
 }
 
+#[cfg(feature = "any_zlib")]
+impl ParCompressBuilder<Zlib> {
+    /// Install `dictionary` as a preset dictionary for every compressor thread this builder
+    /// spawns, and record it on the [`Zlib`] format itself so [`FormatSpec::header`] can set the
+    /// FDICT bit and Adler-32 trailer RFC 1950 requires.
+    ///
+    /// This is the `Zlib`-specific counterpart to the generic
+    /// [`ParCompressBuilder::dictionary`], which installs the same bytes for per-block encoding
+    /// but has no way to reach the stream header; use this instead of that one for `Zlib` so the
+    /// header correctly advertises the dictionary to a decoder.
+    ///
+    /// # Errors
+    /// - [`GzpError::DictionarySize`] if `dictionary` is larger than [`DICT_SIZE`](crate::DICT_SIZE).
+    pub fn with_dictionary(self, dictionary: Vec<u8>) -> Result<Self, GzpError> {
+        let dictionary = Bytes::from(dictionary);
+        let format = Zlib::with_dictionary(dictionary.to_vec());
+        Ok(self.dictionary(dictionary)?.format(format))
+    }
+}
+
+#[cfg(feature = "any_zlib")]
+impl<W: Write> SyncZBuilder<Zlib, W> {
+    /// Create a [`SyncZ`] that installs `dictionary` on its compressor and advertises it via the
+    /// header's FDICT bit and Adler-32 trailer, per RFC 1950.
+    ///
+    /// `Zlib`'s generic [`SyncWriter::sync_writer`] has no way to carry a dictionary through
+    /// (it's a static, stateless trait method), so this bypasses it and builds a
+    /// [`ZlibSyncWriter::with_dictionary`] directly.
+    ///
+    /// # Errors
+    /// - [`GzpError::DictionarySize`] if `dictionary` is larger than [`DICT_SIZE`](crate::DICT_SIZE).
+    pub fn with_dictionary(self, writer: W, dictionary: Bytes) -> Result<SyncZ<ZlibSyncWriter<W>>, GzpError> {
+        Ok(SyncZ {
+            inner: Some(ZlibSyncWriter::with_dictionary(
+                writer,
+                self.compression_level,
+                dictionary,
+            )?),
+        })
+    }
+}
+
 #[cfg(feature = "any_zlib")]
 impl<W> SyncWriter<W> for Zlib
 where
     W: Write,
 {
-    type OutputWriter = ZlibEncoder<W>;
+    type OutputWriter = ZlibSyncWriter<W>;
 
-    fn sync_writer(writer: W, compression_level: Compression) -> ZlibEncoder<W> {
-        ZlibEncoder::new(writer, compression_level)
+    fn sync_writer(writer: W, compression_level: Compression) -> ZlibSyncWriter<W> {
+        ZlibSyncWriter::new(writer, compression_level)
     }
 }
 
 #[cfg(feature = "any_zlib")]
-impl<W: Write> ZWriter for SyncZ<ZlibEncoder<W>> {
+impl<W: Write> ZWriter for SyncZ<ZlibSyncWriter<W>> {
     fn finish(&mut self) -> Result<(), GzpError> {
-        self.inner.take().unwrap().finish()?;
+        self.inner.take().unwrap().flush()?;
         Ok(())
     }
 }
@@ -309,6 +474,7 @@ impl FormatSpec for RawDeflate {
         encoder: &mut Self::Compressor,
         compression_level: Compression,
         dict: Option<&Bytes>,
+        _is_first: bool,
         is_last: bool,
     ) -> Result<Vec<u8>, GzpError> {
         // The plus 8 allows odd small sized blocks to extend up to a byte boundary
@@ -357,7 +523,69 @@ impl<W: Write> ZWriter for SyncZ<DeflateEncoder<W>> {
 
 /// Produce an Mgzip encoder
 #[derive(Copy, Clone, Debug)]
-pub struct Mgzip {}
+pub struct Mgzip {
+    /// Gzip header metadata set via [`Mgzip::with_header_options`], written into the stream's
+    /// first block's header only.
+    ///
+    /// `fname`/`comment` are leaked for the life of the process so `Mgzip` can stay `Copy`, as
+    /// required by [`FormatSpec`], mirroring [`crate::zstd::Zstd::with_dictionary`].
+    fname: Option<&'static [u8]>,
+    comment: Option<&'static [u8]>,
+    mtime: u32,
+    os: u8,
+    xfl: Option<u8>,
+    header_crc: bool,
+    /// Pre-encoded user-defined FEXTRA subfields (see [`mgzip::GzHeaderOptions::extra_subfield`]),
+    /// leaked alongside `fname`/`comment` so `Mgzip` can stay `Copy`.
+    extra: Option<&'static [u8]>,
+}
+
+impl Mgzip {
+    /// Create an [`Mgzip`] format bound to the given gzip header metadata (filename, comment,
+    /// mtime, OS, XFL, FHCRC, extra subfields) -- the parallel-writer equivalent of
+    /// [`MgzipSyncWriter::with_capacity`]'s `header_options` parameter.
+    ///
+    /// Since this metadata describes the stream as a whole, it's written into only the stream's
+    /// first block (determined by submission order, not compression completion order -- see
+    /// [`FormatSpec::encode`]'s `is_first` parameter); every other block gets a bare header, so
+    /// [`crate::par::decompress::ParDecompress`]'s fixed-size block framing isn't disturbed.
+    ///
+    /// FTEXT is ignored: it's a per-stream hint, and doesn't make sense applied to every block of
+    /// a multi-member format (see [`Gzip::with_header_options`]).
+    pub fn with_header_options(options: mgzip::GzHeaderOptions) -> Self {
+        let (fname, comment, mtime, os, xfl, header_crc, _text, extra) = options.into_parts();
+        Self {
+            fname: fname.map(|f| &*Box::leak(f.into_boxed_slice())),
+            comment: comment.map(|c| &*Box::leak(c.into_boxed_slice())),
+            mtime,
+            os,
+            xfl,
+            header_crc,
+            extra: (!extra.is_empty()).then(|| &*Box::leak(extra.into_boxed_slice())),
+        }
+    }
+
+    /// Rebuild the [`mgzip::GzHeaderOptions`] this format was created with.
+    fn header_options(&self) -> mgzip::GzHeaderOptions {
+        let mut options = mgzip::GzHeaderOptions::new()
+            .mtime(self.mtime)
+            .os(self.os)
+            .header_crc(self.header_crc);
+        if let Some(fname) = self.fname {
+            options = options.fname(fname);
+        }
+        if let Some(comment) = self.comment {
+            options = options.comment(comment);
+        }
+        if let Some(xfl) = self.xfl {
+            options = options.xfl(xfl);
+        }
+        if let Some(extra) = self.extra {
+            options = options.with_raw_extra_subfields(extra.to_vec());
+        }
+        options
+    }
+}
 
 impl BlockFormatSpec for Mgzip {
     #[cfg(feature = "libdeflate")]
@@ -423,6 +651,11 @@ impl BlockFormatSpec for Mgzip {
     fn get_block_size(&self, bytes: &[u8]) -> Result<usize, GzpError> {
         Ok(LittleEndian::read_u32(&bytes[16..]) as usize)
     }
+
+    #[inline]
+    fn header_extra_len(&self, header: &[u8], remainder: &[u8]) -> usize {
+        mgzip::header_extra_len(header, remainder)
+    }
 }
 
 #[allow(unused)]
@@ -436,7 +669,15 @@ impl FormatSpec for Mgzip {
     type Compressor = Compress;
 
     fn new() -> Self {
-        Self {}
+        Self {
+            fname: None,
+            comment: None,
+            mtime: 0,
+            os: mgzip::OS_UNKNOWN,
+            xfl: None,
+            header_crc: false,
+            extra: None,
+        }
     }
 
     #[inline]
@@ -469,9 +710,21 @@ impl FormatSpec for Mgzip {
         encoder: &mut Self::Compressor,
         compression_level: Compression,
         dict: Option<&Bytes>,
+        is_first: bool,
         is_last: bool,
     ) -> Result<Vec<u8>, GzpError> {
-        mgzip::compress(input, encoder, compression_level)
+        // Header metadata describes the stream as a whole (filename, comment, ...), so it's only
+        // written into the first block's header; every other block gets a bare IG header.
+        if is_first {
+            mgzip::compress_with_header(input, encoder, compression_level, &self.header_options())
+        } else {
+            mgzip::compress_with_header(
+                input,
+                encoder,
+                compression_level,
+                &mgzip::GzHeaderOptions::default(),
+            )
+        }
     }
 
     fn header(&self, compression_level: Compression) -> Vec<u8> {
@@ -507,7 +760,81 @@ impl<W: Write> ZWriter for SyncZ<MgzipSyncWriter<W>> {
 
 /// Produce an Bgzf encoder
 #[derive(Copy, Clone, Debug)]
-pub struct Bgzf {}
+pub struct Bgzf {
+    /// Gzip header metadata set via [`Bgzf::with_header_options`], written into the stream's
+    /// first block's header only.
+    ///
+    /// `fname`/`comment` are leaked for the life of the process so `Bgzf` can stay `Copy`, as
+    /// required by [`FormatSpec`], mirroring [`Mgzip`]'s fields.
+    fname: Option<&'static [u8]>,
+    comment: Option<&'static [u8]>,
+    mtime: u32,
+    os: u8,
+    xfl: Option<u8>,
+    header_crc: bool,
+    /// Pre-encoded user-defined FEXTRA subfields (see [`mgzip::GzHeaderOptions::extra_subfield`]),
+    /// leaked alongside `fname`/`comment` so `Bgzf` can stay `Copy`.
+    extra: Option<&'static [u8]>,
+}
+
+impl Bgzf {
+    /// Create a [`Bgzf`] format bound to the given gzip header metadata (filename, comment,
+    /// mtime, OS, XFL, FHCRC, extra subfields) -- the parallel-writer equivalent of
+    /// [`BgzfSyncWriter::with_capacity`]'s `header_options` parameter.
+    ///
+    /// Since this metadata describes the stream as a whole, it's written into only the stream's
+    /// first block (determined by submission order, not compression completion order -- see
+    /// [`FormatSpec::encode`]'s `is_first` parameter); every other block gets a bare BGZF header,
+    /// matching how [`BgzfSyncWriter`] only attaches it to its first block.
+    ///
+    /// FTEXT is ignored: it's a per-stream hint, and doesn't make sense applied to every block of
+    /// a multi-member format (see [`Gzip::with_header_options`]).
+    pub fn with_header_options(options: mgzip::GzHeaderOptions) -> Self {
+        let (fname, comment, mtime, os, xfl, header_crc, _text, extra) = options.into_parts();
+        Self {
+            fname: fname.map(|f| &*Box::leak(f.into_boxed_slice())),
+            comment: comment.map(|c| &*Box::leak(c.into_boxed_slice())),
+            mtime,
+            os,
+            xfl,
+            header_crc,
+            extra: (!extra.is_empty()).then(|| &*Box::leak(extra.into_boxed_slice())),
+        }
+    }
+
+    /// Rebuild the [`mgzip::GzHeaderOptions`] this format was created with.
+    fn header_options(&self) -> mgzip::GzHeaderOptions {
+        let mut options = mgzip::GzHeaderOptions::new()
+            .mtime(self.mtime)
+            .os(self.os)
+            .header_crc(self.header_crc);
+        if let Some(fname) = self.fname {
+            options = options.fname(fname);
+        }
+        if let Some(comment) = self.comment {
+            options = options.comment(comment);
+        }
+        if let Some(xfl) = self.xfl {
+            options = options.xfl(xfl);
+        }
+        if let Some(extra) = self.extra {
+            options = options.with_raw_extra_subfields(extra.to_vec());
+        }
+        options
+    }
+
+    /// Fold a block's starting compressed byte offset (as recorded in a
+    /// [`ParCompressBuilder::with_index`](crate::par::compress::ParCompressBuilder::with_index)
+    /// index entry) and an uncompressed offset within that block into a single BAM/htslib-style
+    /// virtual offset: `compressed_offset << 16 | within_block_uncompressed_offset`.
+    ///
+    /// To seek to an uncompressed coordinate, binary search the index for the last entry whose
+    /// uncompressed offset is `<=` the target, then pass its compressed offset here along with
+    /// the remaining distance into the block.
+    pub fn virtual_offset(compressed_offset: u64, within_block_uncompressed_offset: u16) -> u64 {
+        (compressed_offset << 16) | within_block_uncompressed_offset as u64
+    }
+}
 
 impl BlockFormatSpec for Bgzf {
     #[cfg(feature = "libdeflate")]
@@ -557,20 +884,41 @@ impl BlockFormatSpec for Bgzf {
 
     #[inline]
     fn check_header(&self, bytes: &[u8]) -> Result<(), GzpError> {
+        if bytes.len() < Self::HEADER_SIZE {
+            return Err(GzpError::InvalidHeader(
+                "Header shorter than BGZF's fixed prefix",
+            ));
+        }
+        if bytes[0] != 0x1f || bytes[1] != 0x8b {
+            return Err(GzpError::InvalidHeader("Bad gzip magic bytes"));
+        }
+        if bytes[2] != 8 {
+            return Err(GzpError::InvalidHeader(
+                "Unsupported compression method, CM must be 8",
+            ));
+        }
         // Check that the extra field flag is set
         if bytes[3] & 4 != 4 {
-            Err(GzpError::InvalidHeader("Extra field flag not set"))
-        } else if bytes[12] != b'B' || bytes[13] != b'C' {
-            // Check for BC in SID
-            Err(GzpError::InvalidHeader("Bad SID"))
-        } else {
-            Ok(())
+            return Err(GzpError::InvalidHeader("Extra field flag not set"));
         }
+        // Walking the EXTRA field to locate `BC` also validates that it's present.
+        bgzf::find_bsize(bytes).map(|_bsize| ())
     }
 
     #[inline]
     fn get_block_size(&self, bytes: &[u8]) -> Result<usize, GzpError> {
-        Ok(LittleEndian::read_u16(&bytes[16..]) as usize + 1)
+        Ok(bgzf::find_bsize(bytes)? as usize + 1)
+    }
+
+    #[inline]
+    fn is_eof_marker(&self, header: &[u8], remainder: &[u8]) -> bool {
+        header == &bgzf::BGZF_EOF[..Self::HEADER_SIZE]
+            && remainder == &bgzf::BGZF_EOF[Self::HEADER_SIZE..]
+    }
+
+    #[inline]
+    fn header_extra_len(&self, header: &[u8], remainder: &[u8]) -> usize {
+        bgzf::header_extra_len(header, remainder)
     }
 }
 
@@ -587,7 +935,15 @@ impl FormatSpec for Bgzf {
     const DEFAULT_BUFSIZE: usize = BGZF_BLOCK_SIZE;
 
     fn new() -> Self {
-        Self {}
+        Self {
+            fname: None,
+            comment: None,
+            mtime: 0,
+            os: mgzip::OS_UNKNOWN,
+            xfl: None,
+            header_crc: false,
+            extra: None,
+        }
     }
 
     #[inline]
@@ -620,9 +976,18 @@ impl FormatSpec for Bgzf {
         encoder: &mut Self::Compressor,
         compression_level: Compression,
         dict: Option<&Bytes>,
+        is_first: bool,
         is_last: bool,
     ) -> Result<Vec<u8>, GzpError> {
-        let mut bytes = bgzf::compress(input, encoder, compression_level)?;
+        // Header metadata describes the stream as a whole (filename, comment, ...), so it's only
+        // written into the first block's header; every other block gets a bare BGZF header.
+        let header_options = is_first.then(|| self.header_options());
+        let mut bytes = bgzf::compress_with_header(
+            input,
+            encoder,
+            compression_level,
+            header_options.as_ref(),
+        )?;
         if is_last {
             bytes.extend(bgzf::BGZF_EOF);
         }
@@ -674,7 +1039,11 @@ mod test {
     use crate::bgzf::{BgzfSyncReader, BGZF_BLOCK_SIZE};
     use crate::mgzip::MgzipSyncReader;
     use crate::par::compress::{ParCompress, ParCompressBuilder};
-    use crate::par::decompress::ParDecompressBuilder;
+    use crate::par::decompress::{
+        AutoDecompress, AutoDecompressBuilder, GzipParDecompressBuilder, ParDecompressBuilder,
+    };
+    #[cfg(feature = "any_zlib")]
+    use crate::par::decompress::ParZlibDecompressBuilder;
     use crate::syncz::SyncZBuilder;
     use crate::{ZBuilder, ZWriter, BUFSIZE, DICT_SIZE};
 
@@ -746,6 +1115,176 @@ mod test {
         assert_eq!(input.to_vec(), bytes);
     }
 
+    #[test]
+    fn test_gzip_par_decompress() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes, written in small pieces; all 10,000 bytes fit in one default-sized
+        // ParCompress chunk.
+        let input: Vec<u8> = (0..10_000).map(|i| (i % 100) as u8).collect();
+
+        // Compress input to output using several threads. ParCompress<Gzip> writes a single plain
+        // gzip member: one header, the compressed chunks, and one footer.
+        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new()
+            .num_threads(4)
+            .unwrap()
+            .from_writer(out_writer);
+        for chunk in input.chunks(100) {
+            par_gz.write_all(chunk).unwrap();
+        }
+        par_gz.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // A plain `MultiGzDecoder` should still be able to read it...
+        let mut gz = MultiGzDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+
+        // ...and so should gzp's scanning parallel decompressor.
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = GzipParDecompressBuilder::new().from_reader(reader);
+        let mut bytes = vec![];
+        par_d.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
+    #[test]
+    fn test_gzip_par_decompress_single_member() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input: Vec<u8> = (0..10_000).map(|i| (i % 100) as u8).collect();
+
+        // A single thread produces a single gzip member, exercising
+        // `GzipParDecompress`'s serial fast path for a stream with only one member.
+        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new().from_writer(out_writer);
+        par_gz.write_all(&input).unwrap();
+        par_gz.finish().unwrap();
+
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = GzipParDecompressBuilder::new().from_reader(reader);
+        let mut bytes = vec![];
+        par_d.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
+    #[test]
+    fn test_auto_decompress_detects_mgzip() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.gz");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input: Vec<u8> = (0..10_000).map(|i| (i % 100) as u8).collect();
+
+        let mut par_gz: ParCompress<Mgzip> = ParCompressBuilder::new()
+            .num_threads(2)
+            .unwrap()
+            .from_writer(out_writer);
+        par_gz.write_all(&input).unwrap();
+        par_gz.finish().unwrap();
+
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut auto = AutoDecompressBuilder::new().from_reader(reader).unwrap();
+        assert!(matches!(auto, AutoDecompress::Mgzip(_)));
+        assert_eq!(auto.algorithm(), crate::par::decompress::Algorithm::Mgzip);
+
+        let mut bytes = vec![];
+        auto.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
+    #[test]
+    fn test_auto_decompress_detects_plain_gzip() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.gz");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input: Vec<u8> = (0..10_000).map(|i| (i % 100) as u8).collect();
+
+        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new()
+            .num_threads(2)
+            .unwrap()
+            .from_writer(out_writer);
+        par_gz.write_all(&input).unwrap();
+        par_gz.finish().unwrap();
+
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut auto = AutoDecompressBuilder::new().from_reader(reader).unwrap();
+        assert!(matches!(auto, AutoDecompress::Gzip(_)));
+        assert_eq!(auto.algorithm(), crate::par::decompress::Algorithm::Gzip);
+
+        let mut bytes = vec![];
+        auto.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "any_zlib")]
+    fn test_auto_decompress_detects_zlib() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.zz");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input: Vec<u8> = (0..10_000).map(|i| (i % 100) as u8).collect();
+
+        let mut zlib = SyncZBuilder::<Zlib, _>::new().from_writer(out_writer);
+        zlib.write_all(&input).unwrap();
+        zlib.finish().unwrap();
+
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut auto = AutoDecompressBuilder::new().from_reader(reader).unwrap();
+        assert!(matches!(auto, AutoDecompress::Zlib(_)));
+        assert_eq!(auto.algorithm(), crate::par::decompress::Algorithm::Zlib);
+
+        let mut bytes = vec![];
+        auto.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
+    #[test]
+    fn test_auto_decompress_falls_back_to_raw_deflate() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.deflate");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input: Vec<u8> = (0..10_000).map(|i| (i % 100) as u8).collect();
+
+        // Raw DEFLATE has no magic bytes of its own, so this exercises the unconditional fallback
+        // at the end of `AutoDecompressBuilder::from_reader`.
+        let mut deflate = SyncZBuilder::<RawDeflate, _>::new().from_writer(out_writer);
+        deflate.write_all(&input).unwrap();
+        deflate.finish().unwrap();
+
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut auto = AutoDecompressBuilder::new().from_reader(reader).unwrap();
+        assert!(matches!(auto, AutoDecompress::RawDeflate(_)));
+        assert_eq!(auto.algorithm(), crate::par::decompress::Algorithm::RawDeflate);
+
+        let mut bytes = vec![];
+        auto.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
     #[test]
     fn test_simple_drop() {
         let dir = tempdir().unwrap();
@@ -846,22 +1385,31 @@ mod test {
     }
 
     #[test]
-    #[cfg(feature = "any_zlib")]
-    fn test_simple_zlib() {
+    fn test_par_compress_gzip_with_header_options() {
         let dir = tempdir().unwrap();
 
         // Create output file
-        let output_file = dir.path().join("output.txt");
+        let output_file = dir.path().join("output.gz");
         let out_writer = BufWriter::new(File::create(&output_file).unwrap());
 
         // Define input bytes
-        let input = b"\
-        This is a longer test than normal to come up with a bunch of text.\n\
-        We'll read just a few lines at a time.\n\
+        let input = b"
+        This is a longer test than normal to come up with a bunch of text.
+        We'll read just a few lines at a time.
         ";
 
+        let header_options = mgzip::GzHeaderOptions::new()
+            .fname("input.txt")
+            .comment("a test comment")
+            .mtime(12345)
+            .os(mgzip::OS_UNIX)
+            .header_crc(true)
+            .text(true);
+
         // Compress input to output
-        let mut par_gz: ParCompress<Zlib> = ParCompressBuilder::new().from_writer(out_writer);
+        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new()
+            .format(Gzip::with_header_options(header_options))
+            .from_writer(out_writer);
         par_gz.write_all(input).unwrap();
         par_gz.finish().unwrap();
 
@@ -871,82 +1419,504 @@ mod test {
         reader.read_to_end(&mut result).unwrap();
 
         // Decompress it
-        let mut gz = ZlibDecoder::new(&result[..]);
+        let mut gz = GzDecoder::new(&result[..]);
         let mut bytes = vec![];
         gz.read_to_end(&mut bytes).unwrap();
 
         // Assert decompressed output is equal to input
         assert_eq!(input.to_vec(), bytes);
+
+        // Assert the header metadata round-tripped
+        let header = gz.header().unwrap();
+        assert_eq!(header.filename(), Some(&b"input.txt"[..]));
+        assert_eq!(header.comment(), Some(&b"a test comment"[..]));
+        assert_eq!(header.mtime(), 12345);
+        assert_eq!(header.operating_system(), mgzip::OS_UNIX);
+
+        // FTEXT isn't exposed by flate2's `GzHeader`, so check the raw FLG byte directly.
+        assert_eq!(result[3] & mgzip::FTEXT, mgzip::FTEXT);
     }
 
     #[test]
-    #[cfg(feature = "any_zlib")]
-    fn test_simple_zlib_sync() {
+    fn test_par_compress_mgzip_with_header_options_round_trip() {
         let dir = tempdir().unwrap();
 
         // Create output file
-        let output_file = dir.path().join("output.txt");
+        let output_file = dir.path().join("output.gz");
         let out_writer = BufWriter::new(File::create(&output_file).unwrap());
 
-        // Define input bytes
-        let input = b"\
-        This is a longer test than normal to come up with a bunch of text.\n\
-        We'll read just a few lines at a time.\n\
-        ";
+        // Big enough (more than two buffer-sized chunks) to force multiple blocks, so this
+        // actually exercises a non-first block alongside the one carrying the header metadata.
+        let input: Vec<u8> = (0..DICT_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        let header_options = mgzip::GzHeaderOptions::new()
+            .fname("input.txt")
+            .comment("a test comment")
+            .mtime(12345)
+            .os(mgzip::OS_UNIX);
 
         // Compress input to output
-        let mut z = SyncZBuilder::<Zlib, _>::new().from_writer(out_writer);
-        z.write_all(input).unwrap();
-        z.finish().unwrap();
+        let mut par_gz: ParCompress<Mgzip> = ParCompressBuilder::new()
+            .format(Mgzip::with_header_options(header_options))
+            .buffer_size(DICT_SIZE)
+            .unwrap()
+            .from_writer(out_writer);
+        par_gz.write_all(&input).unwrap();
+        par_gz.finish().unwrap();
 
         // Read output back in
-        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut reader = BufReader::new(File::open(&output_file).unwrap());
         let mut result = vec![];
         reader.read_to_end(&mut result).unwrap();
 
-        // Decompress it
-        let mut gz = ZlibDecoder::new(&result[..]);
-        let mut bytes = vec![];
-        gz.read_to_end(&mut bytes).unwrap();
-
-        // Assert decompressed output is equal to input
-        assert_eq!(input.to_vec(), bytes);
+        // Decompress it via the parallel, block-oriented reader -- this is the path that would
+        // mis-decode (or panic) if the header metadata weren't confined to the first block, or if
+        // that block's extra metadata bytes weren't skipped before the deflate payload.
+        let par_reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Mgzip>::new().from_reader(par_reader);
+        let mut par_bytes = vec![];
+        par_d.read_to_end(&mut par_bytes).unwrap();
+        assert_eq!(input, par_bytes);
+
+        // Decompress the first member via the sequential reader, and confirm the header metadata
+        // round-tripped there -- checked on a single small read so only the first (metadata-
+        // carrying) block's member has actually been parsed; every later block is a separate
+        // gzip member with a bare header, so checking `header()` after reading everything would
+        // just see the last one.
+        let mut gz = MgzipSyncReader::new(&result[..]);
+        let mut first_byte = [0u8; 1];
+        gz.read_exact(&mut first_byte).unwrap();
+        let header = gz.header().unwrap();
+        assert_eq!(header.fname, Some(b"input.txt".to_vec()));
+        assert_eq!(header.comment, Some(b"a test comment".to_vec()));
+        assert_eq!(header.mtime, 12345);
+        assert_eq!(header.os, mgzip::OS_UNIX);
     }
 
     #[test]
-    fn test_regression() {
+    fn test_par_compress_bgzf_with_header_options_round_trip() {
         let dir = tempdir().unwrap();
 
         // Create output file
-        let output_file = dir.path().join("output.txt");
+        let output_file = dir.path().join("output.gz");
         let out_writer = BufWriter::new(File::create(&output_file).unwrap());
 
-        // Define input bytes that is 206 bytes long
-        // let input = b"The quick brown fox jumped over the moon\n";
-        let input = [
-            132, 19, 107, 159, 69, 217, 180, 131, 224, 49, 143, 41, 194, 30, 151, 22, 55, 30, 42,
-            139, 219, 62, 123, 44, 148, 144, 88, 233, 199, 126, 110, 65, 6, 87, 51, 215, 17, 253,
-            22, 63, 110, 1, 100, 202, 44, 138, 187, 226, 50, 50, 218, 24, 193, 218, 43, 172, 69,
-            71, 8, 164, 5, 186, 189, 215, 151, 170, 243, 235, 219, 103, 1, 0, 102, 80, 179, 95,
-            247, 26, 168, 147, 139, 245, 177, 253, 94, 82, 146, 133, 103, 223, 96, 34, 128, 237,
-            143, 182, 48, 201, 201, 92, 29, 172, 137, 70, 227, 98, 181, 246, 80, 21, 106, 175, 246,
-            41, 229, 187, 87, 65, 79, 63, 115, 66, 143, 251, 41, 251, 214, 7, 64, 196, 27, 180, 42,
-            132, 116, 211, 148, 44, 177, 137, 91, 119, 245, 156, 78, 24, 253, 69, 38, 52, 152, 115,
-            123, 94, 162, 72, 186, 239, 136, 179, 11, 180, 78, 54, 217, 120, 173, 141, 114, 174,
-            220, 160, 223, 184, 114, 73, 148, 120, 43, 25, 21, 62, 62, 244, 85, 87, 19, 174, 182,
-            227, 228, 70, 153, 5, 92, 51, 161, 9, 140, 199, 244, 241, 151, 236, 81, 211,
-        ];
+        // Big enough (more than two buffer-sized chunks) to force multiple blocks, so this
+        // actually exercises a non-first block alongside the one carrying the header metadata.
+        let input: Vec<u8> = (0..DICT_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect();
+
+        let header_options = mgzip::GzHeaderOptions::new()
+            .fname("input.txt")
+            .comment("a test comment")
+            .mtime(12345)
+            .os(mgzip::OS_UNIX);
 
         // Compress input to output
-        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new()
+        let mut par_gz: ParCompress<Bgzf> = ParCompressBuilder::new()
+            .format(Bgzf::with_header_options(header_options))
             .buffer_size(DICT_SIZE)
             .unwrap()
             .from_writer(out_writer);
-        par_gz.write_all(&input[..]).unwrap();
+        par_gz.write_all(&input).unwrap();
         par_gz.finish().unwrap();
 
         // Read output back in
-        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it via the parallel, block-oriented reader -- this is the path that would
+        // mis-decode (or panic) if the header metadata weren't confined to the first block, or if
+        // that block's extra metadata bytes weren't skipped before the deflate payload.
+        let par_reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Bgzf>::new().from_reader(par_reader);
+        let mut par_bytes = vec![];
+        par_d.read_to_end(&mut par_bytes).unwrap();
+        assert_eq!(input, par_bytes);
+
+        // Decompress the first member via the sequential reader, and confirm the header metadata
+        // round-tripped there -- checked on a single small read so only the first (metadata-
+        // carrying) block's member has actually been parsed; every later block is a separate
+        // BGZF member with a bare header, so checking `header()` after reading everything would
+        // just see the last one.
+        let mut gz = BgzfSyncReader::new(&result[..]);
+        let mut first_byte = [0u8; 1];
+        gz.read_exact(&mut first_byte).unwrap();
+        let header = gz.header().unwrap();
+        assert_eq!(header.fname, Some(b"input.txt".to_vec()));
+        assert_eq!(header.comment, Some(b"a test comment".to_vec()));
+        assert_eq!(header.mtime, 12345);
+        assert_eq!(header.os, mgzip::OS_UNIX);
+    }
+
+    #[test]
+    fn test_par_compress_gzip_with_user_extra_subfield() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.gz");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input = b"some input text";
+
+        // A user-defined extra subfield, written standalone since plain Gzip has no
+        // format-mandated subfield of its own.
+        let header_options =
+            mgzip::GzHeaderOptions::new().extra_subfield(b'X', b'X', b"payload".to_vec());
+
+        // Compress input to output
+        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new()
+            .format(Gzip::with_header_options(header_options))
+            .from_writer(out_writer);
+        par_gz.write_all(input).unwrap();
+        par_gz.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = GzDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input.to_vec(), bytes);
+
+        // The user subfield survives round-trip, via flate2's own generic EXTRA parsing.
+        let header = gz.header().unwrap();
+        assert_eq!(header.extra(), Some(&b"XX\x07\0payload"[..]));
+    }
+
+    #[test]
+    #[cfg(feature = "any_zlib")]
+    fn test_simple_zlib() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"\
+        This is a longer test than normal to come up with a bunch of text.\n\
+        We'll read just a few lines at a time.\n\
+        ";
+
+        // Compress input to output
+        let mut par_gz: ParCompress<Zlib> = ParCompressBuilder::new().from_writer(out_writer);
+        par_gz.write_all(input).unwrap();
+        par_gz.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = ZlibDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "any_zlib")]
+    fn test_simple_zlib_sync() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"\
+        This is a longer test than normal to come up with a bunch of text.\n\
+        We'll read just a few lines at a time.\n\
+        ";
+
+        // Compress input to output
+        let mut z = SyncZBuilder::<Zlib, _>::new().from_writer(out_writer);
+        z.write_all(input).unwrap();
+        z.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = ZlibDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "any_zlib")]
+    fn test_zlib_par_decompress() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes, written in small pieces; all 10,000 bytes fit in one default-sized
+        // ParCompress chunk.
+        let input: Vec<u8> = (0..10_000).map(|i| (i % 100) as u8).collect();
+
+        // Compress input to output, using several threads.
+        let mut par_z: ParCompress<Zlib> = ParCompressBuilder::new()
+            .num_threads(4)
+            .unwrap()
+            .from_writer(out_writer);
+        for chunk in input.chunks(100) {
+            par_z.write_all(chunk).unwrap();
+        }
+        par_z.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // A plain `ZlibDecoder` should still be able to read it...
+        let mut z = ZlibDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        z.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+
+        // ...and so should gzp's scanning parallel decompressor.
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParZlibDecompressBuilder::new().from_reader(reader);
+        let mut bytes = vec![];
+        par_d.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "any_zlib")]
+    fn test_par_compress_with_preset_dictionary() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Many short, similar records: too small individually to build up a useful rolling
+        // window, which is exactly the case a preset dictionary is for.
+        let record = b"2026-07-27T00:00:00Z INFO request completed in 12ms\n";
+        let input = record.repeat(64);
+        let dictionary = Bytes::from_static(record);
+
+        let mut par_gz: ParCompress<Zlib> = ParCompressBuilder::new()
+            .buffer_size(DICT_SIZE)
+            .unwrap()
+            .dictionary(dictionary)
+            .unwrap()
+            .from_writer(out_writer);
+        par_gz.write_all(&input).unwrap();
+        par_gz.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = ZlibDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input, bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "any_zlib")]
+    fn test_par_compress_zlib_with_dictionary_header() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let record = b"2026-07-27T00:00:00Z INFO request completed in 12ms\n";
+        let input = record.repeat(64);
+        let dictionary = record.to_vec();
+
+        let mut par_gz: ParCompress<Zlib> = ParCompressBuilder::new()
+            .buffer_size(DICT_SIZE)
+            .unwrap()
+            .with_dictionary(dictionary.clone())
+            .unwrap()
+            .from_writer(out_writer);
+        par_gz.write_all(&input).unwrap();
+        par_gz.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // The header should advertise FDICT and carry the dictionary's Adler-32.
+        assert_eq!(result[1] & 0x20, 0x20);
+        let mut expected_check = Adler32::new();
+        expected_check.update(&dictionary);
+        assert_eq!(
+            byteorder::BigEndian::read_u32(&result[2..6]),
+            expected_check.sum()
+        );
+
+        // A plain `ZlibDecoder` can't supply the dictionary, so decoding via raw, header-less
+        // `Decompress` (the same mode `Zlib::encode` compresses with) plus an up-front
+        // `set_dictionary` call is the only way to read this stream back -- mirroring how
+        // `Zlib::encode` itself installs the dictionary before compressing, rather than relying
+        // on any in-band signaling from the DEFLATE body.
+        let mut decompressor = flate2::Decompress::new(false);
+        decompressor.set_dictionary(&dictionary).unwrap();
+        let mut output = Vec::with_capacity(input.len());
+        // Skip the 2-byte header and 4-byte dictionary Adler-32 before the deflate body.
+        decompressor
+            .decompress_vec(&result[6..], &mut output, flate2::FlushDecompress::Finish)
+            .unwrap();
+
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    #[cfg(feature = "any_zlib")]
+    fn test_syncz_zlib_with_dictionary_header() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let record = b"2026-07-27T00:00:00Z INFO request completed in 12ms\n";
+        let input = record.repeat(64);
+        let dictionary = record.to_vec();
+
+        let mut z = SyncZBuilder::<Zlib, _>::new()
+            .with_dictionary(out_writer, Bytes::from(dictionary.clone()))
+            .unwrap();
+        z.write_all(&input).unwrap();
+        z.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // The header should advertise FDICT and carry the dictionary's Adler-32.
+        assert_eq!(result[1] & 0x20, 0x20);
+        let mut expected_check = Adler32::new();
+        expected_check.update(&dictionary);
+        assert_eq!(
+            byteorder::BigEndian::read_u32(&result[2..6]),
+            expected_check.sum()
+        );
+
+        let mut decompressor = flate2::Decompress::new(false);
+        decompressor.set_dictionary(&dictionary).unwrap();
+        let mut output = Vec::with_capacity(input.len());
+        decompressor
+            .decompress_vec(&result[6..], &mut output, flate2::FlushDecompress::Finish)
+            .unwrap();
+
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    #[cfg(not(feature = "libdeflate"))]
+    fn test_par_compress_sync_flush() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        // Plain `File`, not `BufWriter`, so a `sync_flush` is actually observable from a second
+        // handle without this test also having to flush an intermediate buffer.
+        let out_writer = File::create(&output_file).unwrap();
+
+        let first = b"first logical record\n".repeat(4);
+        let second = b"second logical record\n".repeat(4);
+
+        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new().from_writer(out_writer);
+        par_gz.write_all(&first).unwrap();
+        par_gz.sync_flush().unwrap();
+
+        // Everything written so far must already be on disk, in order, and form a decodable
+        // deflate prefix -- without waiting for `finish` to write the footer.
+        let checkpoint = std::fs::read(&output_file).unwrap();
+        let header_len = Gzip::new().header(Compression::default()).len();
+        let mut decompressor = Decompress::new(false);
+        let mut decoded = vec![0u8; first.len()];
+        decompressor
+            .decompress(
+                &checkpoint[header_len..],
+                &mut decoded,
+                FlushDecompress::Sync,
+            )
+            .unwrap();
+        assert_eq!(decoded, first);
+
+        // The stream must still be usable afterwards.
+        par_gz.write_all(&second).unwrap();
+        par_gz.finish().unwrap();
+
+        let mut reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+        let mut gz = GzDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        let mut expected = first;
+        expected.extend_from_slice(&second);
+        assert_eq!(expected, bytes);
+    }
+
+    #[test]
+    fn test_regression() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes that is 206 bytes long
+        // let input = b"The quick brown fox jumped over the moon\n";
+        let input = [
+            132, 19, 107, 159, 69, 217, 180, 131, 224, 49, 143, 41, 194, 30, 151, 22, 55, 30, 42,
+            139, 219, 62, 123, 44, 148, 144, 88, 233, 199, 126, 110, 65, 6, 87, 51, 215, 17, 253,
+            22, 63, 110, 1, 100, 202, 44, 138, 187, 226, 50, 50, 218, 24, 193, 218, 43, 172, 69,
+            71, 8, 164, 5, 186, 189, 215, 151, 170, 243, 235, 219, 103, 1, 0, 102, 80, 179, 95,
+            247, 26, 168, 147, 139, 245, 177, 253, 94, 82, 146, 133, 103, 223, 96, 34, 128, 237,
+            143, 182, 48, 201, 201, 92, 29, 172, 137, 70, 227, 98, 181, 246, 80, 21, 106, 175, 246,
+            41, 229, 187, 87, 65, 79, 63, 115, 66, 143, 251, 41, 251, 214, 7, 64, 196, 27, 180, 42,
+            132, 116, 211, 148, 44, 177, 137, 91, 119, 245, 156, 78, 24, 253, 69, 38, 52, 152, 115,
+            123, 94, 162, 72, 186, 239, 136, 179, 11, 180, 78, 54, 217, 120, 173, 141, 114, 174,
+            220, 160, 223, 184, 114, 73, 148, 120, 43, 25, 21, 62, 62, 244, 85, 87, 19, 174, 182,
+            227, 228, 70, 153, 5, 92, 51, 161, 9, 140, 199, 244, 241, 151, 236, 81, 211,
+        ];
+
+        // Compress input to output
+        let mut par_gz: ParCompress<Gzip> = ParCompressBuilder::new()
+            .buffer_size(DICT_SIZE)
+            .unwrap()
+            .from_writer(out_writer);
+        par_gz.write_all(&input[..]).unwrap();
+        par_gz.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
         let mut result = vec![];
         reader.read_to_end(&mut result).unwrap();
 
@@ -988,6 +1958,38 @@ mod test {
         assert_eq!(input.to_vec(), result);
     }
 
+    #[test]
+    fn test_mgzip_decompress_lenient_mode_skips_crc_check() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input = b"This is a test line for the lenient decompression path.\n";
+
+        // Compress input to output
+        let mut par_gz: ParCompress<Mgzip> = ParCompressBuilder::new().from_writer(out_writer);
+        par_gz.write_all(input).unwrap();
+        par_gz.finish().unwrap();
+
+        // Strict mode (the default) round-trips correctly...
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Mgzip>::new().from_reader(reader);
+        let mut result = vec![];
+        par_d.read_to_end(&mut result).unwrap();
+        assert_eq!(input.to_vec(), result);
+
+        // ...and so does lenient mode, it just skips the CRC32/ISIZE recompute.
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Mgzip>::new()
+            .verify(false)
+            .from_reader(reader);
+        let mut result = vec![];
+        par_d.read_to_end(&mut result).unwrap();
+        assert_eq!(input.to_vec(), result);
+    }
+
     #[test]
     fn test_simple_bgzf_etoe_decompress() {
         let dir = tempdir().unwrap();
@@ -1018,6 +2020,159 @@ mod test {
         assert_eq!(input.to_vec(), result);
     }
 
+    #[test]
+    fn test_bgzf_seek_virtual() {
+        let dir = tempdir().unwrap();
+
+        // Create output and index files
+        let output_file = dir.path().join("output.gz");
+        let index_file = dir.path().join("output.gz.gzi");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+        let index_writer = BufWriter::new(File::create(&index_file).unwrap());
+
+        // Define input bytes, long enough to span several blocks.
+        let input: Vec<u8> = (0..100_000).map(|i| (i % 100) as u8).collect();
+
+        // Compress input to output, recording a block index alongside it.
+        let mut par_gz: ParCompress<Bgzf> = ParCompressBuilder::new()
+            .num_threads(4)
+            .unwrap()
+            .with_index(index_writer)
+            .from_writer(out_writer);
+        for chunk in input.chunks(1_000) {
+            par_gz.write_all(chunk).unwrap();
+        }
+        par_gz.finish().unwrap();
+
+        // Load the index and binary search it for the block containing our target offset.
+        let target: usize = 70_000;
+        let index_bytes = {
+            let mut bytes = vec![];
+            BufReader::new(File::open(&index_file).unwrap())
+                .read_to_end(&mut bytes)
+                .unwrap();
+            bytes
+        };
+        let num_entries = LittleEndian::read_u64(&index_bytes[..8]) as usize;
+        let mut best = (0u64, 0u64);
+        for i in 0..num_entries {
+            let offset = 8 + i * 16;
+            let compressed_offset = LittleEndian::read_u64(&index_bytes[offset..]);
+            let uncompressed_offset = LittleEndian::read_u64(&index_bytes[offset + 8..]);
+            if uncompressed_offset as usize <= target {
+                best = (compressed_offset, uncompressed_offset);
+            } else {
+                break;
+            }
+        }
+        let voffset = Bgzf::virtual_offset(best.0, (target as u64 - best.1) as u16);
+
+        // Seek to that virtual offset and confirm the bytes read from there match the input.
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Bgzf>::new().from_seekable_reader(reader);
+        par_d.seek_virtual(voffset).unwrap();
+        let mut result = vec![];
+        par_d.read_to_end(&mut result).unwrap();
+        par_d.finish().unwrap();
+
+        assert_eq!(input[target..].to_vec(), result);
+    }
+
+    #[test]
+    fn test_bgzf_seek_uncompressed() {
+        let dir = tempdir().unwrap();
+
+        // Create output and index files
+        let output_file = dir.path().join("output.gz");
+        let index_file = dir.path().join("output.gz.gzi");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+        let index_writer = BufWriter::new(File::create(&index_file).unwrap());
+
+        // Define input bytes, long enough to span several blocks.
+        let input: Vec<u8> = (0..100_000).map(|i| (i % 100) as u8).collect();
+
+        // Compress input to output, recording a block index alongside it.
+        let mut par_gz: ParCompress<Bgzf> = ParCompressBuilder::new()
+            .num_threads(4)
+            .unwrap()
+            .with_index(index_writer)
+            .from_writer(out_writer);
+        for chunk in input.chunks(1_000) {
+            par_gz.write_all(chunk).unwrap();
+        }
+        par_gz.finish().unwrap();
+
+        // Load the .gzi index, then seek directly by plain uncompressed byte offset.
+        let target: usize = 70_000;
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Bgzf>::new().from_seekable_reader(reader);
+        par_d
+            .load_index(BufReader::new(File::open(&index_file).unwrap()))
+            .unwrap();
+        par_d.seek_uncompressed(target as u64).unwrap();
+        let mut result = vec![];
+        par_d.read_to_end(&mut result).unwrap();
+        par_d.finish().unwrap();
+
+        assert_eq!(input[target..].to_vec(), result);
+    }
+
+    #[test]
+    fn test_bgzf_multi_member_false_stops_at_first_eof_marker() {
+        let dir = tempdir().unwrap();
+
+        // Define two separate BGZF streams, each a complete, independently EOF-terminated member.
+        let first_input = b"first member";
+        let second_input = b"second member";
+
+        let first_member_file = dir.path().join("first.gz");
+        let mut par_gz: ParCompress<Bgzf> = ParCompressBuilder::new()
+            .from_writer(BufWriter::new(File::create(&first_member_file).unwrap()));
+        par_gz.write_all(first_input).unwrap();
+        par_gz.finish().unwrap();
+
+        let second_member_file = dir.path().join("second.gz");
+        let mut par_gz: ParCompress<Bgzf> = ParCompressBuilder::new()
+            .from_writer(BufWriter::new(File::create(&second_member_file).unwrap()));
+        par_gz.write_all(second_input).unwrap();
+        par_gz.finish().unwrap();
+
+        // Concatenate them, as if the second member were an unrelated trailer appended by an
+        // outer container.
+        let output_file = dir.path().join("output.gz");
+        {
+            let mut out_writer = BufWriter::new(File::create(&output_file).unwrap());
+            std::io::copy(
+                &mut File::open(&first_member_file).unwrap(),
+                &mut out_writer,
+            )
+            .unwrap();
+            std::io::copy(
+                &mut File::open(&second_member_file).unwrap(),
+                &mut out_writer,
+            )
+            .unwrap();
+        }
+
+        // With multi_member disabled, only the first member is decoded.
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Bgzf>::new()
+            .multi_member(false)
+            .from_reader(reader);
+        let mut result = vec![];
+        par_d.read_to_end(&mut result).unwrap();
+        par_d.finish().unwrap();
+        assert_eq!(first_input.to_vec(), result);
+
+        // With multi_member enabled (the default), both members are decoded back to back.
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParDecompressBuilder::<Bgzf>::new().from_reader(reader);
+        let mut result = vec![];
+        par_d.read_to_end(&mut result).unwrap();
+        par_d.finish().unwrap();
+        assert_eq!([&first_input[..], &second_input[..]].concat(), result);
+    }
+
     proptest! {
         #[test]
         #[ignore]
@@ -1329,5 +2484,95 @@ mod test {
             // Assert decompressed output is equal to input
             assert_eq!(input.to_vec(), bytes);
         }
+
+        #[test]
+        #[ignore]
+        #[cfg(feature = "zstd")]
+        fn test_all_zstd(
+            input in prop::collection::vec(0..u8::MAX, 1..(DICT_SIZE * 10)),
+            buf_size in DICT_SIZE..BUFSIZE,
+            num_threads in 0..num_cpus::get(),
+            num_threads_decomp in 0..num_cpus::get(),
+            write_size in 1000..1001_usize,
+            comp_level in 1..9_u32
+        ) {
+            use crate::zstd::{Zstd, ZstdSyncReader};
+
+            let dir = tempdir().unwrap();
+
+            // Create output file
+            let output_file = dir.path().join("output.txt");
+            let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+            // Compress input to output
+            let mut par_zstd = ZBuilder::<Zstd, _>::new()
+                    .buffer_size(buf_size)
+                    .num_threads(num_threads)
+                    .compression_level(Compression::new(comp_level))
+                    .from_writer(out_writer);
+
+            for chunk in input.chunks(write_size) {
+                par_zstd.write_all(chunk).unwrap();
+            }
+            par_zstd.finish().unwrap();
+
+            // Read output back in, either in parallel or via the single-threaded sync reader
+            let reader = BufReader::new(File::open(output_file).unwrap());
+            let mut reader: Box<dyn Read> = if num_threads_decomp > 0 {
+                Box::new(ParDecompressBuilder::<Zstd>::new().num_threads(num_threads_decomp).unwrap().from_reader(reader))
+            } else {
+                Box::new(ZstdSyncReader::new(reader))
+            };
+            let mut result = vec![];
+            reader.read_to_end(&mut result).unwrap();
+
+            // Assert decompressed output is equal to input
+            assert_eq!(input.to_vec(), result);
+        }
+
+        #[test]
+        #[ignore]
+        #[cfg(feature = "lz4")]
+        fn test_all_lz4(
+            input in prop::collection::vec(0..u8::MAX, 1..(DICT_SIZE * 10)),
+            buf_size in DICT_SIZE..BUFSIZE,
+            num_threads in 0..num_cpus::get(),
+            num_threads_decomp in 0..num_cpus::get(),
+            write_size in 1000..1001_usize,
+            comp_level in 1..9_u32
+        ) {
+            use crate::lz4::{Lz4, Lz4SyncReader};
+
+            let dir = tempdir().unwrap();
+
+            // Create output file
+            let output_file = dir.path().join("output.txt");
+            let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+            // Compress input to output
+            let mut par_lz4 = ZBuilder::<Lz4, _>::new()
+                    .buffer_size(buf_size)
+                    .num_threads(num_threads)
+                    .compression_level(Compression::new(comp_level))
+                    .from_writer(out_writer);
+
+            for chunk in input.chunks(write_size) {
+                par_lz4.write_all(chunk).unwrap();
+            }
+            par_lz4.finish().unwrap();
+
+            // Read output back in, either in parallel or via the single-threaded sync reader
+            let reader = BufReader::new(File::open(output_file).unwrap());
+            let mut reader: Box<dyn Read> = if num_threads_decomp > 0 {
+                Box::new(ParDecompressBuilder::<Lz4>::new().num_threads(num_threads_decomp).unwrap().from_reader(reader))
+            } else {
+                Box::new(Lz4SyncReader::with_capacity(reader, buf_size))
+            };
+            let mut result = vec![];
+            reader.read_to_end(&mut result).unwrap();
+
+            // Assert decompressed output is equal to input
+            assert_eq!(input.to_vec(), result);
+        }
     }
 }
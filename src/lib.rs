@@ -11,7 +11,9 @@
 //! - BGZF
 //! - Mgzip
 //! - Raw Deflate
+//! - LZ4 Frame
 //! - Snap Frame Encoding
+//! - Zstd
 //!
 //! # References
 //!
@@ -94,11 +96,17 @@ pub mod bgzf;
 pub mod check;
 #[cfg(feature = "deflate")]
 pub mod deflate;
+#[cfg(feature = "lz4")]
+pub mod lz4;
 pub mod mgzip;
 pub mod par;
 #[cfg(feature = "snappy")]
 pub mod snap;
 pub mod syncz;
+#[cfg(feature = "any_zlib")]
+pub mod zlib;
+#[cfg(feature = "zstd")]
+pub mod zstd;
 
 /// 128 KB default buffer size, same as pigz.
 pub const BUFSIZE: usize = 64 * (1 << 10) * 2;
@@ -110,6 +118,52 @@ pub const DICT_SIZE: usize = 32768;
 /// a receiver that will receive a result that is a tuple of the check value and the compressed bytes.
 pub type CompressResult<C> = Result<(C, Vec<u8>), GzpError>;
 
+/// Which deflate implementation backs [`Compress`](flate2::Compress)/[`Decompress`](flate2::Decompress)
+/// for the deflate-family formats (Gzip/Zlib/Mgzip/Bgzf), as actually linked into this binary.
+///
+/// flate2 links exactly one C (or pure-Rust) deflate implementation per binary, chosen by which
+/// of its own Cargo features are enabled when gzp is built -- there is no way to link more than
+/// one and pick between them at runtime. `Backend` makes that choice a documented, checkable
+/// value instead of something only visible by reading flate2's feature resolution:
+/// [`ParCompressBuilder::backend`](crate::par::compress::ParCompressBuilder::backend) and
+/// [`ZBuilder::backend`] compare a requested `Backend` against
+/// [`Backend::compiled`] and fail fast with [`GzpError::UnsupportedBackend`] instead of silently
+/// running whatever was actually linked.
+///
+/// gzp can't tell zlib-ng or cloudflare-zlib apart from stock zlib at this level -- they all
+/// present the same C API to flate2 -- so all three report as [`Backend::Zlib`]; only the
+/// pure-Rust [`Backend::MinizOxide`] fallback and gzp's own `libdeflate`-backed path are
+/// distinguishable.
+///
+/// [`Backend::compiled`] reports whether the `libdeflate` feature is on, but only
+/// [`Mgzip`](crate::deflate::Mgzip) and [`Bgzf`](crate::deflate::Bgzf) actually route their block
+/// (de)compression through it -- `Gzip` and `Zlib` always use flate2's `Compress`/`Decompress`
+/// regardless of that feature, so `backend()` on a builder for one of those formats is checking
+/// the wrong thing if `libdeflate` is enabled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// The pure-Rust `miniz_oxide` implementation flate2 falls back to when no C zlib backend is
+    /// enabled.
+    MinizOxide,
+    /// A C zlib-compatible implementation (stock zlib, zlib-ng, or cloudflare-zlib), linked in via
+    /// one of flate2's `zlib`/`zlib-ng`/`cloudflare-zlib` Cargo features.
+    Zlib,
+    /// The `libdeflate` implementation, via gzp's own `libdeflate` feature.
+    Libdeflate,
+}
+
+impl Backend {
+    /// The backend actually compiled into this binary.
+    pub const fn compiled() -> Self {
+        #[cfg(feature = "libdeflate")]
+        return Backend::Libdeflate;
+        #[cfg(all(not(feature = "libdeflate"), feature = "any_zlib"))]
+        return Backend::Zlib;
+        #[cfg(not(any(feature = "libdeflate", feature = "any_zlib")))]
+        return Backend::MinizOxide;
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum GzpError {
     #[error("Invalid buffer size ({0}), must be >= {1}")]
@@ -130,6 +184,9 @@ pub enum GzpError {
     #[error(transparent)]
     DeflateCompress(#[from] flate2::CompressError),
 
+    #[error("Preset dictionary size ({0}) exceeds the DEFLATE window: ({1})")]
+    DictionarySize(usize, usize),
+
     #[error("Invalid block size: {0}")]
     InvalidBlockSize(&'static str),
 
@@ -142,6 +199,10 @@ pub enum GzpError {
     #[error(transparent)]
     Io(#[from] io::Error),
 
+    #[cfg(feature = "snappy")]
+    #[error(transparent)]
+    Snap(#[from] ::snap::Error),
+
     #[cfg(feature = "libdeflate")]
     #[error("LibDeflater compression error: {0:?}")]
     LibDeflaterCompress(libdeflater::CompressionError),
@@ -157,6 +218,12 @@ pub enum GzpError {
     #[error("Invalid number of threads ({0}) selected.")]
     NumThreads(usize),
 
+    #[error("Operation was interrupted")]
+    Interrupted,
+
+    #[error("Requested backend {0:?} is not the one compiled into this binary ({1:?})")]
+    UnsupportedBackend(Backend, Backend),
+
     #[error("Unknown")]
     Unknown,
 }
@@ -165,6 +232,24 @@ pub enum GzpError {
 pub trait ZWriter: Write {
     /// Cleans up resources, writes footers
     fn finish(&mut self) -> Result<(), GzpError>;
+
+    /// Force a flush that makes all bytes written so far decompressible by a reader, without
+    /// ending the stream.
+    ///
+    /// Unlike [`Write::flush`], which only guarantees buffered bytes have been *handed off*
+    /// towards the underlying writer, `sync_flush` guarantees the underlying writer has actually
+    /// received everything written so far, in order, and that it forms a decodable prefix (e.g.
+    /// via `Z_SYNC_FLUSH`). The compressor/dictionary state is left intact, so writes after this
+    /// call continue the same logical stream.
+    ///
+    /// The default implementation is appropriate for the single-threaded writers in this crate,
+    /// whose `flush` already blocks on the underlying `W` directly.
+    ///
+    /// # Errors
+    /// - [`GzpError`] if the flush fails.
+    fn sync_flush(&mut self) -> Result<(), GzpError> {
+        self.flush().map_err(GzpError::from)
+    }
 }
 
 /// Create a synchronous writer wrapping the input `W` type.
@@ -230,6 +315,25 @@ where
         self
     }
 
+    /// Assert that `backend` matches the deflate implementation actually compiled into this
+    /// binary (see [`Backend`]), failing fast with [`GzpError::UnsupportedBackend`] instead of
+    /// silently running whatever was actually linked.
+    ///
+    /// This can't swap backends at runtime -- flate2 links exactly one per binary -- so getting a
+    /// different one means rebuilding gzp with different Cargo features; this setter only makes
+    /// the expectation explicit and checkable.
+    ///
+    /// # Errors
+    /// - [`GzpError::UnsupportedBackend`] if `backend` isn't the one compiled in.
+    pub fn backend(self, backend: Backend) -> Result<Self, GzpError> {
+        let compiled = Backend::compiled();
+        if backend == compiled {
+            Ok(self)
+        } else {
+            Err(GzpError::UnsupportedBackend(backend, compiled))
+        }
+    }
+
     /// Create a [`ZWriter`] trait object from a writer.
     #[allow(clippy::missing_panics_doc)]
     pub fn from_writer(self, writer: W) -> Box<dyn ZWriter>
@@ -280,6 +384,11 @@ where
     oneshot: Sender<CompressResult<C>>,
     dictionary: Option<Bytes>,
     is_last: bool,
+    /// Whether this is the very first chunk of the stream, set by the producer thread (which
+    /// constructs chunks in submission order) since compressor threads pull from a shared queue
+    /// in no particular order. Lets a format embed stream-level metadata into exactly one block
+    /// deterministically -- see [`Mgzip::with_header_options`](crate::deflate::Mgzip::with_header_options).
+    is_first: bool,
 }
 
 impl<C> Message<C>
@@ -298,6 +407,7 @@ where
                 oneshot: tx,
                 dictionary,
                 is_last: false,
+                is_first: false,
             },
             rx,
         )
@@ -314,6 +424,14 @@ pub struct Pair {
 }
 
 /// Defines how to write the header and footer for each format.
+///
+/// This is what makes [`ParCompress`](crate::par::compress::ParCompress) generic over output
+/// codec rather than hardcoded to one: [`Gzip`](crate::deflate::Gzip),
+/// [`Zlib`](crate::deflate::Zlib), [`RawDeflate`](crate::deflate::RawDeflate),
+/// [`Mgzip`](crate::deflate::Mgzip), [`Bgzf`](crate::deflate::Bgzf), [`Lz4`](crate::lz4::Lz4),
+/// [`Snap`](crate::snap::Snap), and [`Zstd`](crate::zstd::Zstd) are all just implementations of
+/// this trait, each pairing the codec with its own [`Check`] type (CRC32, Adler32, or a
+/// pass-through where the format has no trailer checksum of its own).
 pub trait FormatSpec: Clone + Copy + Debug + Send + Sync + 'static {
     /// The Check type for this format.
     type C: Check + Send + 'static;
@@ -341,12 +459,19 @@ pub trait FormatSpec: Clone + Copy + Debug + Send + Sync + 'static {
     ) -> Result<Self::Compressor, GzpError>;
 
     /// How to deflate bytes for this format. Returns deflated bytes.
+    ///
+    /// `is_first` is set on the chunk the producer constructed first, regardless of the order
+    /// compressor threads happen to finish in; formats that embed metadata into only one block
+    /// (e.g. [`Mgzip`](crate::deflate::Mgzip)'s [`with_header_options`](crate::deflate::Mgzip::with_header_options))
+    /// use it to gate that metadata to the stream's actual first block.
+    #[allow(clippy::too_many_arguments)]
     fn encode(
         &self,
         input: &[u8],
         encoder: &mut Self::Compressor,
         compression_level: Compression,
         dict: Option<&Bytes>,
+        is_first: bool,
         is_last: bool,
     ) -> Result<Vec<u8>, GzpError>;
 
@@ -411,6 +536,13 @@ pub trait BlockFormatSpec: FormatSpec {
 
     const HEADER_SIZE: usize;
 
+    /// Number of trailing footer bytes every block ends with, which [`ParDecompress`](crate::par::decompress::ParDecompress)'s
+    /// worker strips off before handing a block's bytes to [`Self::decode_block`]. Defaults to 8
+    /// (a 4-byte checksum plus a 4-byte amount, the shape [`Self::get_footer_values`]'s default
+    /// impl reads), matching every format except [`crate::snap::Snap`], whose checksum sits at the
+    /// *front* of each chunk instead, so it overrides this to 0.
+    const FOOTER_SIZE: usize = 8;
+
     /// Create a Decompressor for this format
     fn create_decompressor(&self) -> Self::Decompressor;
 
@@ -428,6 +560,26 @@ pub trait BlockFormatSpec: FormatSpec {
     /// Check that the header is expected for this format
     fn get_block_size(&self, _bytes: &[u8]) -> Result<usize, GzpError>;
 
+    /// Whether a just-read block (`header` is its [`Self::HEADER_SIZE`]-byte header, `remainder`
+    /// everything after it, as declared by [`Self::get_block_size`]) is this format's explicit
+    /// end-of-stream marker, e.g. BGZF's empty EOF block. Formats with no such marker (the
+    /// default) always return `false`, so their streams only ever end at a clean EOF.
+    #[inline]
+    fn is_eof_marker(&self, _header: &[u8], _remainder: &[u8]) -> bool {
+        false
+    }
+
+    /// Number of leading bytes within `remainder` (after the fixed [`Self::HEADER_SIZE`]-byte
+    /// header and before the deflate payload) that are variable-length per-block metadata rather
+    /// than compressed data, e.g. [`crate::deflate::Mgzip`]'s optional FEXTRA/FNAME/FCOMMENT/FHCRC
+    /// bytes, which [`crate::deflate::Mgzip::with_header_options`] writes into the stream's first
+    /// block only. [`ParDecompress`](crate::par::decompress::ParDecompress) skips this many bytes
+    /// of `remainder` before handing the rest to [`Self::decode_block`]. Defaults to 0.
+    #[inline]
+    fn header_extra_len(&self, _header: &[u8], _remainder: &[u8]) -> usize {
+        0
+    }
+
     /// Get the check value and check sum from the footer
     #[inline]
     fn get_footer_values(&self, input: &[u8]) -> FooterValues {
@@ -0,0 +1,649 @@
+//! LZ4 frame compression format.
+//!
+//! Each block is compressed as an independent LZ4 frame, mirroring [`crate::mgzip`] and
+//! [`crate::zstd`], so blocks can be produced and consumed in parallel rather than needing to
+//! share compressor state across the whole stream. The frame sets the block-independence flag
+//! for exactly this reason. Each gzp block is wrapped in a small fixed-size header recording the
+//! total block size (so a parallel reader can skip straight to the next block) and suffixed with
+//! an [`check::XxHash32`] footer recording the checksum and uncompressed size of the block, used
+//! to both validate and size the output buffer on decompression.
+//!
+//! [`Lz4`] is a [`BlockFormatSpec`] peer to [`crate::deflate::Bgzf`], with [`Lz4SyncWriter`]/
+//! [`Lz4SyncReader`] for the sync path and the usual [`ParCompress`](crate::par::compress::ParCompress)/
+//! [`ParDecompress`](crate::par::decompress::ParDecompress) generic machinery for the parallel
+//! one: same 4-byte LZ4 frame magic, FLG/BD descriptor and header checksum, block-size field with
+//! its high bit marking an uncompressed (stored) block, an optional per-block `xxh32` checksum,
+//! `EndMark`, and an optional content checksum laid out in the standard frame format linked below
+//! -- both checksums default on, configurable per-instance via [`Lz4::with_checksums`]. Block compression
+//! itself is [`lz4_flex::block`], used directly rather than through `lz4_flex`'s own frame codec,
+//! since each gzp block already needs to build and parse frame bytes by hand to fit
+//! [`BlockFormatSpec`]'s contract. Because [`Lz4`] is a plain [`FormatSpec`] like every other
+//! format here, it slots into [`crate::ZBuilder`], [`ParCompressBuilder`](crate::par::compress::ParCompressBuilder),
+//! and [`crate::syncz::SyncZBuilder`] with no format-specific wiring, which is what a CLI that
+//! dispatches on a format enum (e.g. crabz) needs.
+//!
+//! # References
+//!
+//! - [LZ4 Frame Format](https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md)
+//! - [lz4_flex](https://docs.rs/lz4_flex)
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "lz4")] {
+//! use std::{env, fs::File, io::Write};
+//!
+//! use gzp::{lz4::Lz4, par::compress::{ParCompressBuilder, ParCompress}, ZWriter};
+//!
+//! let mut writer = vec![];
+//! let mut parz: ParCompress<Lz4> = ParCompressBuilder::new().from_writer(writer);
+//! parz.write_all(b"This is a first test line\n").unwrap();
+//! parz.write_all(b"This is a second test line\n").unwrap();
+//! parz.finish().unwrap();
+//! # }
+//! ```
+
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use bytes::{Buf, Bytes, BytesMut};
+use flate2::Compression;
+use twox_hash::XxHash32;
+
+use crate::check::{self, Check, PassThroughCheck};
+use crate::{BlockFormatSpec, FooterValues, FormatSpec, GzpError, SyncWriter, ZWriter, BUFSIZE};
+
+/// Magic bytes identifying a gzp Lz4 block wrapper, not to be confused with [`LZ4_MAGIC`] which
+/// follows immediately after and identifies the real LZ4 frame embedded inside.
+pub(crate) const WRAPPER_MAGIC: [u8; 4] = *b"LZ4B";
+/// 4 wrapper magic bytes + a 4-byte LE total block size.
+const HEADER_SIZE: usize = 8;
+/// 4-byte LE [`check::XxHash32`] sum + 4-byte LE uncompressed size.
+const FOOTER_SIZE: usize = 8;
+
+/// The real LZ4 frame magic number.
+const LZ4_MAGIC: u32 = 0x184D_2204;
+/// BD: block max size index 4 (64 KB). Descriptive only: gzp's own blocksize governs how much
+/// goes into a single LZ4 block, not this field.
+const BD: u8 = 0b0100_0000;
+/// The zero-size block that terminates an LZ4 frame's block sequence.
+const END_MARK: u32 = 0;
+/// High bit of the per-block size field, set when the block is stored uncompressed rather than
+/// LZ4-compressed (e.g. because compression didn't shrink it).
+const UNCOMPRESSED_FLAG: u32 = 0x8000_0000;
+
+/// FLG bits shared by every frame this module writes: version `01` plus the block independence
+/// flag (bit 5). [`flg_byte`] adds the block- and content-checksum bits on top of this depending on
+/// what's configured.
+const FLG_BASE: u8 = 0b0110_0000;
+/// FLG bit 4: a per-block `xxh32` checksum follows each block's compressed bytes.
+const FLG_BLOCK_CHECKSUM: u8 = 0b0001_0000;
+/// FLG bit 2: a frame-level `xxh32` checksum of the uncompressed content follows the end mark.
+const FLG_CONTENT_CHECKSUM: u8 = 0b0000_0100;
+
+/// The `FLG` byte for a frame with the given optional checksums enabled.
+#[inline]
+fn flg_byte(block_checksum: bool, content_checksum: bool) -> u8 {
+    let mut flg = FLG_BASE;
+    if block_checksum {
+        flg |= FLG_BLOCK_CHECKSUM;
+    }
+    if content_checksum {
+        flg |= FLG_CONTENT_CHECKSUM;
+    }
+    flg
+}
+
+/// Produce an independent LZ4 frame per block.
+///
+/// Whether a block checksum and/or a content checksum are written is configurable via
+/// [`Lz4::with_checksums`] (both default to on, matching the standard LZ4 CLI's `-BX -Cx`
+/// defaults); decoding never depends on this, since each frame's own `FLG` byte records which
+/// ones it carries.
+#[derive(Copy, Clone, Debug)]
+pub struct Lz4 {
+    block_checksum: bool,
+    content_checksum: bool,
+}
+
+impl Lz4 {
+    /// Create an [`Lz4`] format with the block-checksum and content-checksum frame flags set
+    /// explicitly, rather than the default of both enabled.
+    pub fn with_checksums(block_checksum: bool, content_checksum: bool) -> Self {
+        Self {
+            block_checksum,
+            content_checksum,
+        }
+    }
+}
+
+/// The header checksum (HC) byte: the second byte of the `XxHash32` digest of the frame
+/// descriptor bytes that precede it (here, just `FLG` and `BD`, since content size is unset).
+#[inline]
+fn frame_header_checksum(flg: u8, bd: u8) -> u8 {
+    let mut hasher = XxHash32::with_seed(0);
+    hasher.write(&[flg, bd]);
+    (hasher.finish() >> 8) as u8
+}
+
+/// Create a gzp block header, recording the total block size (header + frame + footer).
+#[inline]
+fn header_inner(frame_size: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(&WRAPPER_MAGIC);
+    header
+        .write_u32::<LittleEndian>(frame_size + HEADER_SIZE as u32 + FOOTER_SIZE as u32)
+        .unwrap();
+    header
+}
+
+/// Build a complete, standards-conformant LZ4 frame around a single LZ4 block.
+#[inline]
+fn build_frame(input: &[u8], block_checksum: bool, content_checksum: bool) -> Vec<u8> {
+    let compressed = lz4_flex::block::compress(input);
+    let (block, stored) = if compressed.len() < input.len() {
+        (compressed, false)
+    } else {
+        (input.to_vec(), true)
+    };
+
+    let flg = flg_byte(block_checksum, content_checksum);
+
+    let mut frame = Vec::with_capacity(7 + 8 + block.len() + 8);
+    frame.write_u32::<LittleEndian>(LZ4_MAGIC).unwrap();
+    frame.push(flg);
+    frame.push(BD);
+    frame.push(frame_header_checksum(flg, BD));
+
+    let size_field = block.len() as u32 | if stored { UNCOMPRESSED_FLAG } else { 0 };
+    frame.write_u32::<LittleEndian>(size_field).unwrap();
+    frame.extend_from_slice(&block);
+    if block_checksum {
+        let mut block_check = XxHash32::with_seed(0);
+        block_check.write(&block);
+        frame
+            .write_u32::<LittleEndian>(block_check.finish() as u32)
+            .unwrap();
+    }
+
+    frame.write_u32::<LittleEndian>(END_MARK).unwrap();
+    if content_checksum {
+        let mut content_check = XxHash32::with_seed(0);
+        content_check.write(input);
+        frame
+            .write_u32::<LittleEndian>(content_check.finish() as u32)
+            .unwrap();
+    }
+
+    frame
+}
+
+/// Compress a block of bytes into a self-contained LZ4 frame, adding a gzp header and footer.
+/// `block_checksum`/`content_checksum` control which optional `FLG` checksums the frame carries;
+/// see [`Lz4::with_checksums`].
+#[inline]
+pub fn compress(input: &[u8], block_checksum: bool, content_checksum: bool) -> Result<Vec<u8>, GzpError> {
+    let frame = build_frame(input, block_checksum, content_checksum);
+
+    let mut check = check::XxHash32::new();
+    check.update(input);
+
+    let mut buffer = header_inner(frame.len() as u32);
+    buffer.extend(frame);
+    buffer.write_u32::<LittleEndian>(check.sum()).unwrap();
+    buffer.write_u32::<LittleEndian>(check.amount()).unwrap();
+    Ok(buffer)
+}
+
+/// Decompress the single LZ4 block embedded in a gzp-framed LZ4 frame, `frame`, into `output`.
+///
+/// `frame` is the real LZ4 frame bytes (magic through the trailing content checksum), without
+/// gzp's own header or footer.
+#[inline]
+fn decode_frame(frame: &[u8], output: &mut [u8]) -> Result<(), GzpError> {
+    if frame.len() < 11 {
+        return Err(GzpError::InvalidBlockSize(
+            "LZ4 frame is too short to hold its own header",
+        ));
+    }
+    if LittleEndian::read_u32(&frame[..4]) != LZ4_MAGIC {
+        return Err(GzpError::InvalidHeader("Bad LZ4 frame magic"));
+    }
+    if frame[6] != frame_header_checksum(frame[4], frame[5]) {
+        return Err(GzpError::InvalidHeader("Bad LZ4 frame header checksum"));
+    }
+
+    let size_field = LittleEndian::read_u32(&frame[7..11]);
+    let stored = size_field & UNCOMPRESSED_FLAG != 0;
+    let block_len = (size_field & !UNCOMPRESSED_FLAG) as usize;
+    let block = frame.get(11..11 + block_len).ok_or(GzpError::InvalidBlockSize(
+        "LZ4 frame's declared block length runs past the frame bytes available",
+    ))?;
+
+    if stored {
+        output.copy_from_slice(block);
+    } else {
+        lz4_flex::block::decompress_into(block, output)
+            .map_err(|_e| GzpError::InvalidBlockSize("LZ4 block decompression failed"))?;
+    }
+    Ok(())
+}
+
+/// Decompress a single LZ4-framed block into `output`, checking it against the [`check::XxHash32`]
+/// checksum carried in `footer_vals`.
+#[inline]
+pub fn decompress(input: &[u8], output: &mut [u8], footer_vals: FooterValues) -> Result<(), GzpError> {
+    if footer_vals.amount != 0 {
+        decode_frame(&input[..input.len() - FOOTER_SIZE], output)?;
+    }
+    let mut check = check::XxHash32::new();
+    check.update(output);
+    if check.sum() != footer_vals.sum {
+        return Err(GzpError::InvalidCheck {
+            found: check.sum(),
+            expected: footer_vals.sum,
+        });
+    }
+    Ok(())
+}
+
+impl FormatSpec for Lz4 {
+    type C = PassThroughCheck;
+    // LZ4 block compression is stateless, so there's no persistent encoder to reuse.
+    type Compressor = ();
+
+    fn new() -> Self {
+        Self {
+            block_checksum: true,
+            content_checksum: true,
+        }
+    }
+
+    #[inline]
+    fn needs_dict(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn create_compressor(&self, _compression_level: Compression) -> Result<Self::Compressor, GzpError> {
+        Ok(())
+    }
+
+    #[inline]
+    fn encode(
+        &self,
+        input: &[u8],
+        _compressor: &mut Self::Compressor,
+        _compression_level: Compression,
+        _dict: Option<&Bytes>,
+        _is_first: bool,
+        _is_last: bool,
+    ) -> Result<Vec<u8>, GzpError> {
+        compress(input, self.block_checksum, self.content_checksum)
+    }
+
+    fn header(&self, _compression_level: Compression) -> Vec<u8> {
+        vec![]
+    }
+
+    fn footer(&self, _check: &Self::C) -> Vec<u8> {
+        vec![]
+    }
+}
+
+impl BlockFormatSpec for Lz4 {
+    type B = check::XxHash32;
+    // LZ4 block (de)compression is stateless, so there's no persistent decoder to reuse.
+    type Decompressor = ();
+
+    const HEADER_SIZE: usize = HEADER_SIZE;
+
+    fn create_decompressor(&self) -> Self::Decompressor {}
+
+    #[inline]
+    fn decode_block(
+        &self,
+        _decoder: &mut Self::Decompressor,
+        input: &[u8],
+        orig_size: usize,
+    ) -> Result<Vec<u8>, GzpError> {
+        let mut output = vec![0u8; orig_size];
+        decode_frame(input, &mut output)?;
+        Ok(output)
+    }
+
+    #[inline]
+    fn check_header(&self, bytes: &[u8]) -> Result<(), GzpError> {
+        if bytes[..4] != WRAPPER_MAGIC[..] {
+            Err(GzpError::InvalidHeader("Bad LZ4 block wrapper magic"))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn get_block_size(&self, bytes: &[u8]) -> Result<usize, GzpError> {
+        Ok(LittleEndian::read_u32(&bytes[4..]) as usize)
+    }
+}
+
+/// A synchronous implementation of an LZ4 block writer.
+///
+/// **NOTE** use [`Lz4`] via [`crate::par::compress::ParCompress`] for a parallel implementation.
+/// **NOTE** this uses an internal buffer already so the passed in writer almost certainly does not
+/// need to be a BufferedWriter.
+pub struct Lz4SyncWriter<W>
+where
+    W: Write,
+{
+    /// The internal buffer to use
+    buffer: BytesMut,
+    /// The size of the blocks to create
+    blocksize: usize,
+    /// The format instance whose [`Lz4::with_checksums`] setting governs every block this writer
+    /// produces.
+    format: Lz4,
+    /// The inner writer
+    writer: W,
+}
+
+impl<W> Lz4SyncWriter<W>
+where
+    W: Write,
+{
+    /// Create a new [`Lz4SyncWriter`] with both optional checksums enabled.
+    pub fn new(writer: W) -> Self {
+        Self::with_capacity(writer, BUFSIZE)
+    }
+
+    pub fn with_capacity(writer: W, blocksize: usize) -> Self {
+        Self {
+            buffer: BytesMut::with_capacity(BUFSIZE),
+            blocksize,
+            format: Lz4::new(),
+            writer,
+        }
+    }
+
+    /// Create a new [`Lz4SyncWriter`] with the given format settings (e.g. from
+    /// [`Lz4::with_checksums`]).
+    pub fn with_format(writer: W, blocksize: usize, format: Lz4) -> Self {
+        Self {
+            buffer: BytesMut::with_capacity(BUFSIZE),
+            blocksize,
+            format,
+            writer,
+        }
+    }
+}
+
+impl<W> Write for Lz4SyncWriter<W>
+where
+    W: Write,
+{
+    /// Write a buffer into this writer, returning how many bytes were written.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= self.blocksize {
+            let b = self.buffer.split_to(self.blocksize).freeze();
+            let compressed = compress(&b[..], self.format.block_checksum, self.format.content_checksum)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.writer.write_all(&compressed)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Flush this output stream, ensuring all intermediately buffered contents are sent.
+    fn flush(&mut self) -> std::io::Result<()> {
+        let b = self.buffer.split_to(self.buffer.len()).freeze();
+        if !b.is_empty() {
+            let compressed = compress(&b[..], self.format.block_checksum, self.format.content_checksum)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.writer.write_all(&compressed)?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W> Drop for Lz4SyncWriter<W>
+where
+    W: Write,
+{
+    fn drop(&mut self) {
+        self.flush().unwrap();
+    }
+}
+
+impl<W> SyncWriter<W> for Lz4
+where
+    W: Write,
+{
+    type OutputWriter = Lz4SyncWriter<W>;
+
+    /// Compression level is ignored; LZ4 block compression has no level knob.
+    fn sync_writer(writer: W, _compression_level: Compression) -> Self::OutputWriter {
+        Lz4SyncWriter::new(writer)
+    }
+}
+
+impl<W: Write> ZWriter for crate::syncz::SyncZ<Lz4SyncWriter<W>> {
+    fn finish(&mut self) -> Result<(), GzpError> {
+        self.inner.take().unwrap().flush()?;
+        Ok(())
+    }
+}
+
+/// A synchronous implementation of an LZ4 block reader.
+///
+/// **NOTE** this uses an internal buffer already so the passed in reader almost certainly does not
+/// need to be a BufferedReader.
+pub struct Lz4SyncReader<R>
+where
+    R: Read,
+{
+    buffer: BytesMut,
+    compressed_buffer: BytesMut,
+    reader: R,
+    format: Lz4,
+}
+
+impl<R> Lz4SyncReader<R>
+where
+    R: Read,
+{
+    /// Create a new reader.
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(reader, BUFSIZE)
+    }
+
+    /// Create a new reader with a specified capacity
+    pub fn with_capacity(reader: R, blocksize: usize) -> Self {
+        Self {
+            buffer: BytesMut::with_capacity(blocksize),
+            compressed_buffer: BytesMut::with_capacity(blocksize),
+            reader,
+            format: Lz4::new(),
+        }
+    }
+}
+
+impl<R> Read for Lz4SyncReader<R>
+where
+    R: Read,
+{
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut total_read = 0;
+        loop {
+            let before = self.buffer.remaining();
+            if before > buf.len() - total_read {
+                self.buffer.copy_to_slice(&mut buf[total_read..]);
+            } else if !self.buffer.is_empty() {
+                self.buffer
+                    .copy_to_slice(&mut buf[total_read..total_read + before]);
+            }
+            let after = self.buffer.remaining();
+            total_read += before - after;
+
+            if total_read == buf.len() {
+                break;
+            } else if total_read <= buf.len() {
+                let mut header_buf = vec![0; HEADER_SIZE];
+                if let Ok(()) = self.reader.read_exact(&mut header_buf) {
+                    self.format
+                        .check_header(&header_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let size = self
+                        .format
+                        .get_block_size(&header_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+                    let remaining = size.checked_sub(HEADER_SIZE).filter(|r| *r >= FOOTER_SIZE).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            GzpError::InvalidBlockSize(
+                                "LZ4 block size is too small for its own header and footer",
+                            ),
+                        )
+                    })?;
+
+                    self.compressed_buffer.clear();
+                    self.compressed_buffer.resize(remaining, 0);
+                    self.reader.read_exact(&mut self.compressed_buffer)?;
+
+                    let check = self.format.get_footer_values(&self.compressed_buffer);
+                    self.buffer.clear();
+                    self.buffer.resize(check.amount as usize, 0);
+
+                    decompress(&self.compressed_buffer, &mut self.buffer, check)
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        Ok(total_read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::{
+        fs::File,
+        io::{BufReader, BufWriter},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::par::compress::{ParCompress, ParCompressBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_simple_lz4sync() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"
+        This is a longer test than normal to come up with a bunch of text.
+        We'll read just a few lines at a time.
+        ";
+
+        // Compress input to output
+        let mut lz4 = Lz4SyncWriter::new(out_writer);
+        lz4.write_all(input).unwrap();
+        lz4.flush().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = Lz4SyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_par_compress_lz4() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"
+        This is a longer test than normal to come up with a bunch of text.
+        We'll read just a few lines at a time.
+        ";
+
+        // Compress input to output
+        let mut par_lz4: ParCompress<Lz4> = ParCompressBuilder::new().from_writer(out_writer);
+        par_lz4.write_all(input).unwrap();
+        par_lz4.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = Lz4SyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_lz4sync_rejects_garbage_header() {
+        let garbage = [0x00u8; 32];
+        let mut decoder = Lz4SyncReader::new(&garbage[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_lz4sync_rejects_truncated_block() {
+        let mut out_buf = vec![];
+        let mut lz4 = Lz4SyncWriter::new(&mut out_buf);
+        lz4.write_all(b"hello, world").unwrap();
+        lz4.flush().unwrap();
+        drop(lz4);
+
+        // Chop off the last 4 bytes of the 8-byte footer so the block's declared size runs past
+        // the data actually available.
+        out_buf.truncate(out_buf.len() - 4);
+
+        let mut decoder = Lz4SyncReader::new(&out_buf[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_lz4sync_rejects_undersized_block() {
+        // A header whose declared size leaves no room for even the footer must be rejected
+        // before the `size - HEADER_SIZE` subtraction underflows.
+        let mut header = WRAPPER_MAGIC.to_vec();
+        header
+            .write_u32::<LittleEndian>(HEADER_SIZE as u32)
+            .unwrap();
+
+        let mut decoder = Lz4SyncReader::new(&header[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+}
@@ -6,29 +6,241 @@
 use std::io::Write;
 use std::io::{self, Read};
 
-use byteorder::{LittleEndian, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use bytes::{Buf, BytesMut};
-use flate2::Compression;
+use flate2::{Compression, Decompress, FlushDecompress, Status};
 #[cfg(not(feature = "libdeflate"))]
-use flate2::{Compress, Decompress, FlushCompress};
+use flate2::{Compress, FlushCompress};
 
 #[cfg(not(feature = "libdeflate"))]
 use crate::check::Check;
 use crate::deflate::Mgzip;
-use crate::{BlockFormatSpec, FooterValues, GzpError, BUFSIZE};
-
-#[cfg(feature = "libdeflate")]
-const MGZIP_HEADER_SIZE: usize = 20;
-#[cfg(feature = "libdeflate")]
-const MGZIP_FOOTER_SIZE: usize = 8;
+use crate::{BlockFormatSpec, FooterValues, FormatSpec, GzpError, BUFSIZE};
 
 const EXTRA: f64 = 0.1;
 
+/// RFC 1952 FLG bit for text data.
+pub(crate) const FTEXT: u8 = 0x01;
+/// RFC 1952 FLG bit indicating a header CRC16 follows the header.
+pub(crate) const FHCRC: u8 = 0x02;
+/// RFC 1952 FLG bit indicating an extra field is present. Always set for mgzip; set for plain
+/// [`crate::deflate::Gzip`] only when [`GzHeaderOptions::extra_subfield`] was used.
+pub(crate) const FEXTRA: u8 = 0x04;
+/// RFC 1952 FLG bit indicating a NUL-terminated original filename follows.
+pub(crate) const FNAME: u8 = 0x08;
+/// RFC 1952 FLG bit indicating a NUL-terminated comment follows.
+pub(crate) const FCOMMENT: u8 = 0x10;
+
+/// Named OS byte values from RFC 1952 §2.3.1.2, not exhaustive -- only the ones gzip
+/// implementations in the wild actually produce.
+pub const OS_FAT: u8 = 0;
+pub const OS_UNIX: u8 = 3;
+pub const OS_MACINTOSH: u8 = 7;
+pub const OS_NTFS: u8 = 11;
+/// The default: OS unknown.
+pub const OS_UNKNOWN: u8 = 255;
+
 #[inline]
 fn extra_amount(input_len: usize) -> usize {
     std::cmp::max(128, (input_len as f64 * EXTRA) as usize)
 }
 
+/// Optional gzip header metadata written into each mgzip member's header.
+///
+/// By default this produces the same minimal header `gzp` has always emitted: no
+/// filename/comment, `mtime = 0`, `OS = 255` (unknown), and no header CRC.
+///
+/// Also reused by [`crate::bgzf::BgzfSyncWriter`], which writes it into only the first block of a
+/// BGZF stream.
+#[derive(Clone, Debug, Default)]
+pub struct GzHeaderOptions {
+    /// The original filename (FNAME), written as a NUL-terminated ISO-8859-1 string.
+    fname: Option<Vec<u8>>,
+    /// A free-form comment (FCOMMENT), written as a NUL-terminated ISO-8859-1 string.
+    comment: Option<Vec<u8>>,
+    /// The modification time (MTIME), seconds since the Unix epoch, or 0 if unknown.
+    mtime: u32,
+    /// The OS byte, defaults to [`OS_UNKNOWN`].
+    os: u8,
+    /// The XFL byte. `None` (the default) derives it from the compression level the way `gzp`
+    /// always has; `Some` overrides that with a fixed value.
+    xfl: Option<u8>,
+    /// Whether to emit the FHCRC header CRC16.
+    header_crc: bool,
+    /// Whether to set the FTEXT flag, hinting that the uncompressed data is ASCII text. Only
+    /// honored by [`crate::deflate::Gzip`]; mgzip/BGZF headers never set it.
+    text: bool,
+    /// User-defined FEXTRA subfields, pre-encoded (SI1, SI2, SLEN, payload per RFC 1952
+    /// §2.3.1.1) and written after any format-mandated subfield (mgzip's `IG`, BGZF's `BC`) so
+    /// they're invisible to the fixed-offset block framing those formats rely on.
+    extra_subfields: Vec<u8>,
+}
+
+impl GzHeaderOptions {
+    /// Create a new [`GzHeaderOptions`] with the default (empty) metadata.
+    pub fn new() -> Self {
+        Self {
+            fname: None,
+            comment: None,
+            mtime: 0,
+            os: OS_UNKNOWN,
+            xfl: None,
+            header_crc: false,
+            text: false,
+            extra_subfields: Vec::new(),
+        }
+    }
+
+    /// Set the original filename (FNAME).
+    pub fn fname(mut self, fname: impl Into<Vec<u8>>) -> Self {
+        self.fname = Some(fname.into());
+        self
+    }
+
+    /// Set the comment (FCOMMENT).
+    pub fn comment(mut self, comment: impl Into<Vec<u8>>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Set the modification time (MTIME).
+    pub fn mtime(mut self, mtime: u32) -> Self {
+        self.mtime = mtime;
+        self
+    }
+
+    /// Set the OS byte. See [`OS_UNIX`] and friends for named values.
+    pub fn os(mut self, os: u8) -> Self {
+        self.os = os;
+        self
+    }
+
+    /// Set the XFL byte, overriding the value `gzp` would otherwise derive from the compression
+    /// level.
+    pub fn xfl(mut self, xfl: u8) -> Self {
+        self.xfl = Some(xfl);
+        self
+    }
+
+    /// Opt into writing the FHCRC header CRC16.
+    pub fn header_crc(mut self, header_crc: bool) -> Self {
+        self.header_crc = header_crc;
+        self
+    }
+
+    /// Opt into setting the FTEXT flag, hinting that the uncompressed data is ASCII text. Only
+    /// honored by [`crate::deflate::Gzip`]; mgzip/BGZF headers never set it.
+    pub fn text(mut self, text: bool) -> Self {
+        self.text = text;
+        self
+    }
+
+    /// Append a user-defined FEXTRA subfield (`si1`, `si2`, and its payload). May be called more
+    /// than once; subfields are written in the order added, after any format-mandated subfield
+    /// (mgzip's `IG`, BGZF's `BC`).
+    pub fn extra_subfield(mut self, si1: u8, si2: u8, data: impl Into<Vec<u8>>) -> Self {
+        let data = data.into();
+        self.extra_subfields.push(si1);
+        self.extra_subfields.push(si2);
+        self.extra_subfields
+            .write_u16::<LittleEndian>(data.len() as u16)
+            .unwrap();
+        self.extra_subfields.extend(data);
+        self
+    }
+
+    /// Install already-encoded FEXTRA subfield bytes directly, used by
+    /// [`crate::deflate::Mgzip::header_options`] to rebuild a [`GzHeaderOptions`] from the raw
+    /// bytes `Mgzip` leaked in [`Mgzip::with_header_options`](crate::deflate::Mgzip::with_header_options).
+    pub(crate) fn with_raw_extra_subfields(mut self, raw: Vec<u8>) -> Self {
+        self.extra_subfields = raw;
+        self
+    }
+
+    /// Decompose into raw parts, used by [`crate::deflate::Mgzip::with_header_options`] and
+    /// [`crate::deflate::Gzip::with_header_options`] to leak them for the life of the process so
+    /// those formats can stay `Copy`.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        Option<Vec<u8>>,
+        Option<Vec<u8>>,
+        u32,
+        u8,
+        Option<u8>,
+        bool,
+        bool,
+        Vec<u8>,
+    ) {
+        (
+            self.fname,
+            self.comment,
+            self.mtime,
+            self.os,
+            self.xfl,
+            self.header_crc,
+            self.text,
+            self.extra_subfields,
+        )
+    }
+
+    /// Borrow the configured fields without consuming `self`, used by
+    /// [`crate::bgzf::BgzfSyncWriter`] to reuse this builder for its own, differently-shaped
+    /// header.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn as_parts(
+        &self,
+    ) -> (
+        Option<&[u8]>,
+        Option<&[u8]>,
+        u32,
+        u8,
+        Option<u8>,
+        bool,
+        bool,
+        &[u8],
+    ) {
+        (
+            self.fname.as_deref(),
+            self.comment.as_deref(),
+            self.mtime,
+            self.os,
+            self.xfl,
+            self.header_crc,
+            self.text,
+            &self.extra_subfields,
+        )
+    }
+}
+
+/// The gzip header metadata parsed from a member, the decompression-side counterpart to
+/// [`GzHeaderOptions`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GzHeader {
+    /// The original filename (FNAME), if present.
+    pub fname: Option<Vec<u8>>,
+    /// The free-form comment (FCOMMENT), if present.
+    pub comment: Option<Vec<u8>>,
+    /// The modification time (MTIME), seconds since the Unix epoch, or 0 if unknown.
+    pub mtime: u32,
+    /// The OS byte, 255 if unknown.
+    pub os: u8,
+    /// Any EXTRA subfields other than mgzip's mandatory `IG` block-size subfield.
+    pub extra: Vec<u8>,
+}
+
+/// An entry in a [`MgzipSyncReader`] block index, as built by [`MgzipSyncReader::build_index`].
+#[derive(Debug, Clone, Copy)]
+struct BlockIndexEntry {
+    /// Offset of this block's header in the underlying compressed stream.
+    compressed_offset: u64,
+    /// Offset of this block's first uncompressed byte in the decompressed stream.
+    uncompressed_offset: u64,
+    /// Number of uncompressed bytes in this block.
+    uncompressed_len: u32,
+}
+
 /// A synchronous implementation of an Mgzip reader.
 pub struct MgzipSyncReader<R>
 where
@@ -42,6 +254,18 @@ where
     decompressor: Decompress,
     reader: R,
     format: Mgzip,
+    /// Block boundary index built lazily by [`MgzipSyncReader::build_index`] the first time the
+    /// reader is seeked.
+    index: Vec<BlockIndexEntry>,
+    /// The current position in the uncompressed stream, tracked so `Seek::seek` can honor
+    /// `SeekFrom::Current`.
+    uncompressed_pos: u64,
+    /// Bytes already pulled from `reader` but not yet consumed, left over from decoding a plain
+    /// (non-mgzip) gzip member whose compressed length isn't known up front. Drained before the
+    /// next read from `reader`.
+    leftover: BytesMut,
+    /// The gzip header metadata parsed from the most recently decoded member, if any.
+    header: Option<GzHeader>,
 }
 
 impl<R> MgzipSyncReader<R>
@@ -66,8 +290,293 @@ where
             compressed_buffer: BytesMut::with_capacity(blocksize),
             decompressor,
             reader,
-            format: Mgzip {},
+            format: Mgzip::new(),
+            index: Vec::new(),
+            uncompressed_pos: 0,
+            leftover: BytesMut::new(),
+            header: None,
+        }
+    }
+
+    /// The gzip header metadata (filename, comment, mtime, OS, extra subfields) parsed from the
+    /// most recently decoded member, or `None` if no member has been read yet.
+    pub fn header(&self) -> Option<&GzHeader> {
+        self.header.as_ref()
+    }
+
+    /// Read up to `buf.len()` bytes, preferring bytes already pulled into `self.leftover` before
+    /// reading fresh bytes from `self.reader`.
+    fn read_some(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.leftover.is_empty() {
+            let n = std::cmp::min(buf.len(), self.leftover.len());
+            self.leftover.copy_to_slice(&mut buf[..n]);
+            Ok(n)
+        } else {
+            self.reader.read(buf)
+        }
+    }
+
+    /// Like [`std::io::Read::read_exact`], but drains `self.leftover` first.
+    fn read_exact_buffered(&mut self, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let n = self.read_some(buf)?;
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "unexpected EOF while reading a gzip member",
+                ));
+            }
+            buf = &mut buf[n..];
+        }
+        Ok(())
+    }
+
+    /// Read a NUL-terminated field (FNAME or FCOMMENT), returning its bytes (excluding the
+    /// terminator).
+    fn read_nul_terminated(&mut self) -> io::Result<Vec<u8>> {
+        let mut field = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            self.read_exact_buffered(&mut byte)?;
+            if byte[0] == 0 {
+                return Ok(field);
+            }
+            field.push(byte[0]);
+        }
+    }
+
+    /// Read and decode the next gzip member into `self.buffer`.
+    ///
+    /// Understands two member shapes:
+    /// - An mgzip block: the mandatory `IG` extra subfield stores the size of the complete block
+    ///   (header, compressed body and 8-byte footer), so the rest of it can be read in one shot.
+    /// - A standard RFC 1952 member, as produced by `gzip`/`pigz`, or a plain trailing member in
+    ///   an otherwise-mgzip stream: the `IG` subfield is absent, so the DEFLATE body is inflated
+    ///   incrementally until the stream ends, then the 8-byte CRC32+ISIZE trailer is read and
+    ///   checked.
+    ///
+    /// Returns `Ok(false)` at a clean EOF before a new member, `Ok(true)` once a member has been
+    /// decoded into `self.buffer`.
+    fn read_member(&mut self) -> io::Result<bool> {
+        let mut fixed = [0u8; 10];
+        let n = self.read_some(&mut fixed[..1])?;
+        if n == 0 {
+            return Ok(false);
+        }
+        self.read_exact_buffered(&mut fixed[1..])?;
+        let mut consumed = fixed.len();
+
+        if fixed[0] != 0x1f || fixed[1] != 0x8b {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                GzpError::InvalidHeader("Bad gzip magic bytes"),
+            ));
+        }
+        if fixed[2] != 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                GzpError::InvalidHeader("Unsupported compression method, CM must be 8"),
+            ));
+        }
+        let flags = fixed[3];
+        let mtime = LittleEndian::read_u32(&fixed[4..8]);
+        let os = fixed[9];
+
+        let mut mgzip_block_size = None;
+        let mut extra_other = Vec::new();
+        if flags & FEXTRA != 0 {
+            let mut xlen_buf = [0u8; 2];
+            self.read_exact_buffered(&mut xlen_buf)?;
+            let xlen = LittleEndian::read_u16(&xlen_buf) as usize;
+            let mut extra = vec![0u8; xlen];
+            self.read_exact_buffered(&mut extra)?;
+            consumed += 2 + xlen;
+
+            // Scan the subfields for mgzip's `IG` subfield, keeping any others around to expose
+            // via `GzHeader::extra`.
+            let mut i = 0;
+            while i + 4 <= extra.len() {
+                let si1 = extra[i];
+                let si2 = extra[i + 1];
+                let subfield_len = LittleEndian::read_u16(&extra[i + 2..]) as usize;
+                let subfield_end = std::cmp::min(i + 4 + subfield_len, extra.len());
+                if si1 == b'I' && si2 == b'G' && subfield_len == 4 && i + 8 <= extra.len() {
+                    mgzip_block_size = Some(LittleEndian::read_u32(&extra[i + 4..]));
+                } else {
+                    extra_other.extend_from_slice(&extra[i..subfield_end]);
+                }
+                i = subfield_end;
+            }
+        }
+        let fname = if flags & FNAME != 0 {
+            let field = self.read_nul_terminated()?;
+            consumed += field.len() + 1;
+            Some(field)
+        } else {
+            None
+        };
+        let comment = if flags & FCOMMENT != 0 {
+            let field = self.read_nul_terminated()?;
+            consumed += field.len() + 1;
+            Some(field)
+        } else {
+            None
+        };
+        if flags & FHCRC != 0 {
+            let mut discard = [0u8; 2];
+            self.read_exact_buffered(&mut discard)?;
+            consumed += 2;
+        }
+
+        self.header = Some(GzHeader {
+            fname,
+            comment,
+            mtime,
+            os,
+            extra: extra_other,
+        });
+
+        if let Some(block_size) = mgzip_block_size {
+            let remaining = block_size as usize - consumed;
+            let mut compressed_buffer = std::mem::take(&mut self.compressed_buffer);
+            compressed_buffer.clear();
+            compressed_buffer.resize(remaining, 0);
+            self.read_exact_buffered(&mut compressed_buffer)?;
+            self.compressed_buffer = compressed_buffer;
+
+            let check = self.format.get_footer_values(&self.compressed_buffer);
+            self.buffer.clear();
+            self.buffer.resize(check.amount as usize, 0);
+            decompress(
+                &self.compressed_buffer,
+                &mut self.decompressor,
+                &mut self.buffer,
+                check,
+            )
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        } else {
+            self.decode_plain_member()?;
+        }
+
+        Ok(true)
+    }
+
+    /// Inflate a standard RFC 1952 gzip member (no mgzip `IG` extra field), whose compressed
+    /// length isn't known up front: feed the DEFLATE decompressor incrementally until it reports
+    /// the stream has ended, pushing back any bytes it didn't need, then read and check the
+    /// 8-byte CRC32+ISIZE trailer.
+    fn decode_plain_member(&mut self) -> io::Result<()> {
+        let mut decompressor = Decompress::new(false);
+        let mut output = Vec::new();
+        let mut chunk = [0u8; 4096];
+
+        loop {
+            let n = self.read_some(&mut chunk)?;
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "gzip member ended before the DEFLATE stream finished",
+                ));
+            }
+            // `decompress_vec` only writes into spare capacity, so make room for this chunk's output.
+            output.reserve(4 * chunk.len());
+            let before_in = decompressor.total_in();
+            let status = decompressor
+                .decompress_vec(&chunk[..n], &mut output, FlushDecompress::None)
+                .map_err(GzpError::from)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let consumed = (decompressor.total_in() - before_in) as usize;
+
+            if consumed < n {
+                // `read_some` always drains `self.leftover` before pulling fresh bytes, so it is
+                // empty here; the unconsumed tail of this chunk becomes the new leftover.
+                self.leftover = BytesMut::from(&chunk[consumed..n]);
+            }
+
+            if status == Status::StreamEnd {
+                break;
+            }
+        }
+
+        let mut trailer = [0u8; 8];
+        self.read_exact_buffered(&mut trailer)?;
+        let expected_crc = LittleEndian::read_u32(&trailer[..4]);
+        let expected_isize = LittleEndian::read_u32(&trailer[4..]);
+
+        let mut check = flate2::Crc::new();
+        check.update(&output);
+        if check.sum() != expected_crc {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                GzpError::InvalidCheck {
+                    found: check.sum(),
+                    expected: expected_crc,
+                },
+            ));
+        }
+        if output.len() as u32 != expected_isize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                GzpError::InvalidBlockSize("decompressed size does not match ISIZE trailer"),
+            ));
+        }
+
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&output);
+        Ok(())
+    }
+}
+
+impl<R> MgzipSyncReader<R>
+where
+    R: Read + io::Seek,
+{
+    /// Scan the whole stream, reading only each block's 20-byte header and 8-byte footer, and
+    /// build an index of block boundaries. This enables [`std::io::Seek`] to jump directly to
+    /// the block containing a target uncompressed offset instead of decompressing from the
+    /// start.
+    ///
+    /// The reader's position is restored to where it was before the call.
+    pub fn build_index(&mut self) -> io::Result<()> {
+        let start = self.reader.stream_position()?;
+
+        let mut index = Vec::new();
+        let mut compressed_offset = 0u64;
+        let mut uncompressed_offset = 0u64;
+        let mut header_buf = vec![0; Mgzip::HEADER_SIZE];
+        self.reader.seek(io::SeekFrom::Start(0))?;
+        loop {
+            if self.reader.read_exact(&mut header_buf).is_err() {
+                break;
+            }
+            self.format
+                .check_header(&header_buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let size = self
+                .format
+                .get_block_size(&header_buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            let mut footer_buf = [0; 8];
+            self.reader
+                .seek(io::SeekFrom::Start(compressed_offset + size as u64 - 8))?;
+            self.reader.read_exact(&mut footer_buf)?;
+            let uncompressed_len = LittleEndian::read_u32(&footer_buf[4..]);
+
+            index.push(BlockIndexEntry {
+                compressed_offset,
+                uncompressed_offset,
+                uncompressed_len,
+            });
+
+            compressed_offset += size as u64;
+            uncompressed_offset += u64::from(uncompressed_len);
+            self.reader.seek(io::SeekFrom::Start(compressed_offset))?;
         }
+
+        self.index = index;
+        self.reader.seek(io::SeekFrom::Start(start))?;
+        Ok(())
     }
 }
 
@@ -91,8 +600,18 @@ where
     compressor: libdeflater::Compressor,
     #[cfg(not(feature = "libdeflate"))]
     compressor: Compress,
+    /// The gzip header metadata (filename, comment, mtime, OS, FHCRC) to write into each block.
+    header_options: GzHeaderOptions,
     /// The inner writer
     writer: W,
+    /// An opt-in sink for a companion block index, set via [`MgzipSyncWriter::with_index`].
+    index_writer: Option<Box<dyn Write>>,
+    /// Cumulative compressed/uncompressed byte offsets, tracked only while `index_writer` is set.
+    compressed_offset: u64,
+    uncompressed_offset: u64,
+    /// `(compressed_offset, uncompressed_offset)` pairs recorded at each block boundary,
+    /// serialized to `index_writer` the first time [`MgzipSyncWriter::flush`] is called.
+    index_entries: Vec<(u64, u64)>,
 }
 
 impl<W> MgzipSyncWriter<W>
@@ -101,10 +620,15 @@ where
 {
     /// Create a new [`MgzipSyncWriter`]
     pub fn new(writer: W, compression_level: Compression) -> Self {
-        Self::with_capacity(writer, compression_level, BUFSIZE)
+        Self::with_capacity(writer, compression_level, BUFSIZE, GzHeaderOptions::default())
     }
 
-    pub fn with_capacity(writer: W, compression_level: Compression, blocksize: usize) -> Self {
+    pub fn with_capacity(
+        writer: W,
+        compression_level: Compression,
+        blocksize: usize,
+        header_options: GzHeaderOptions,
+    ) -> Self {
         #[cfg(feature = "libdeflate")]
         let compressor = libdeflater::Compressor::new(
             libdeflater::CompressionLvl::new(compression_level.level() as i32).unwrap(),
@@ -116,9 +640,35 @@ where
             blocksize,
             compression_level,
             compressor,
+            header_options,
             writer,
+            index_writer: None,
+            compressed_offset: 0,
+            uncompressed_offset: 0,
+            index_entries: Vec::new(),
         }
     }
+
+    /// Opt into writing a companion GZI-style block index alongside the compressed output: a
+    /// little-endian `u64` entry count followed by that many `(compressed_offset,
+    /// uncompressed_offset)` pairs of little-endian `u64`s, one per block boundary.
+    ///
+    /// Loadable by [`crate::par::decompress::ParDecompress::load_index`] to make a
+    /// [`crate::par::decompress::ParDecompress`] seekable.
+    pub fn with_index<W2: Write + 'static>(mut self, index_writer: W2) -> Self {
+        self.index_writer = Some(Box::new(index_writer));
+        self
+    }
+
+    /// Record a block boundary for the optional index, if one was requested.
+    fn record_block_boundary(&mut self, uncompressed_len: usize, compressed_len: usize) {
+        if self.index_writer.is_some() {
+            self.index_entries
+                .push((self.compressed_offset, self.uncompressed_offset));
+            self.uncompressed_offset += uncompressed_len as u64;
+        }
+        self.compressed_offset += compressed_len as u64;
+    }
 }
 
 /// Decompress a block of bytes
@@ -182,28 +732,32 @@ pub fn compress(
     encoder: &mut libdeflater::Compressor,
     compression_level: Compression,
 ) -> Result<Vec<u8>, GzpError> {
-    // The plus 64 allows odd small sized blocks to extend up to a byte boundary
-    // let mut buffer = Vec::with_capacity(input.len() + 64);
-    let mut buffer =
-        vec![0; MGZIP_HEADER_SIZE + input.len() + extra_amount(input.len()) + MGZIP_FOOTER_SIZE];
-    // let mut encoder = libdeflater::Compressor::new(
-    //     libdeflater::CompressionLvl::new(compression_level.level() as i32)
-    //         .map_err(|e| GzpError::LibDeflaterCompressionLvl(e))?,
-    // );
+    compress_with_header(input, encoder, compression_level, &GzHeaderOptions::default())
+}
 
+/// Compress a block of bytes, adding a header (carrying the given metadata) and footer.
+#[cfg(feature = "libdeflate")]
+#[inline]
+pub fn compress_with_header(
+    input: &[u8],
+    encoder: &mut libdeflater::Compressor,
+    compression_level: Compression,
+    header_options: &GzHeaderOptions,
+) -> Result<Vec<u8>, GzpError> {
+    // The plus 64 allows odd small sized blocks to extend up to a byte boundary
+    let mut compressed = vec![0; input.len() + extra_amount(input.len())];
     let bytes_written = encoder
-        .deflate_compress(input, &mut buffer[MGZIP_HEADER_SIZE..])
+        .deflate_compress(input, &mut compressed)
         .map_err(GzpError::LibDeflaterCompress)?;
+    compressed.truncate(bytes_written);
 
     let mut check = libdeflater::Crc::new();
     check.update(input);
 
     // Add header with total byte sizes
-    let header = header_inner(compression_level, bytes_written as u32);
-    buffer[0..MGZIP_HEADER_SIZE].copy_from_slice(&header);
-    buffer.truncate(MGZIP_HEADER_SIZE + bytes_written);
-
-    // let mut footer = Vec::with_capacity(8);
+    let header = header_inner(compression_level, bytes_written as u32, header_options);
+    let mut buffer = header;
+    buffer.extend(compressed);
     buffer.write_u32::<LittleEndian>(check.sum())?;
     buffer.write_u32::<LittleEndian>(input.len() as u32)?;
 
@@ -216,6 +770,17 @@ pub fn compress(
     input: &[u8],
     encoder: &mut Compress,
     compression_level: Compression,
+) -> Result<Vec<u8>, GzpError> {
+    compress_with_header(input, encoder, compression_level, &GzHeaderOptions::default())
+}
+
+#[cfg(not(feature = "libdeflate"))]
+#[inline]
+pub fn compress_with_header(
+    input: &[u8],
+    encoder: &mut Compress,
+    compression_level: Compression,
+    header_options: &GzHeaderOptions,
 ) -> Result<Vec<u8>, GzpError> {
     // The plus 64 allows odd small sized blocks to extend up to a byte boundary
     let mut buffer = Vec::with_capacity(input.len() + extra_amount(input.len()));
@@ -227,46 +792,158 @@ pub fn compress(
     check.update(input);
 
     // Add header with total byte sizes
-    let mut header = header_inner(compression_level, buffer.len() as u32);
+    let mut header = header_inner(compression_level, buffer.len() as u32, header_options);
     let footer = footer_inner(&check);
     header.extend(buffer.into_iter().chain(footer));
     encoder.reset();
     Ok(header)
 }
 
-/// Create an mgzip style header
+/// Create an mgzip style header, including any optional FNAME/FCOMMENT/FHCRC fields requested
+/// by `header_options`.
 #[inline]
-fn header_inner(compression_level: Compression, compressed_size: u32) -> Vec<u8> {
-    // Size = header + extra subfield size + filename with null terminator (if present) + datablock size (unknknown) + footer
-    // const size: u32  = 16 + 4 + 0 + 0 + 8;
-
-    let comp_value = if compression_level.level() >= Compression::best().level() {
-        2
-    } else if compression_level.level() <= Compression::fast().level() {
-        4
-    } else {
-        0
-    };
+fn header_inner(
+    compression_level: Compression,
+    compressed_size: u32,
+    header_options: &GzHeaderOptions,
+) -> Vec<u8> {
+    let xfl = header_options.xfl.unwrap_or_else(|| {
+        if compression_level.level() >= Compression::best().level() {
+            2
+        } else if compression_level.level() <= Compression::fast().level() {
+            4
+        } else {
+            0
+        }
+    });
+
+    let mut flags = FEXTRA;
+    if header_options.fname.is_some() {
+        flags |= FNAME;
+    }
+    if header_options.comment.is_some() {
+        flags |= FCOMMENT;
+    }
+    if header_options.header_crc {
+        flags |= FHCRC;
+    }
 
     let mut header = Vec::with_capacity(20);
     header.write_u8(31).unwrap(); // magic byte
     header.write_u8(139).unwrap(); // magic byte
     header.write_u8(8).unwrap(); // compression method
-    header.write_u8(4).unwrap(); // name / comment / extraflag
-    header.write_u32::<LittleEndian>(0).unwrap(); // mtime
-    header.write_u8(comp_value).unwrap(); // compression value
-    header.write_u8(255).unwrap(); // OS
-    header.write_u16::<LittleEndian>(8).unwrap(); // Extra flag len
+    header.write_u8(flags).unwrap(); // FLG
+    header
+        .write_u32::<LittleEndian>(header_options.mtime)
+        .unwrap(); // mtime
+    header.write_u8(xfl).unwrap(); // XFL
+    header.write_u8(header_options.os).unwrap(); // OS
+    let user_extra = &header_options.extra_subfields;
+    header
+        .write_u16::<LittleEndian>(8 + user_extra.len() as u16)
+        .unwrap(); // Extra flag len
     header.write_u8(b'I').unwrap(); // mgzip subfield ID 1
     header.write_u8(b'G').unwrap(); // mgzip subfield ID2
     header.write_u16::<LittleEndian>(4).unwrap(); // mgzip sufield len
+
+    // Optional FNAME/FCOMMENT/FHCRC fields follow the mandatory IG subfield (and any
+    // user-supplied subfields), and their length must be folded into the block size recorded in
+    // that subfield.
+    let mut optional = Vec::new();
+    if let Some(fname) = &header_options.fname {
+        optional.extend_from_slice(fname);
+        optional.push(0);
+    }
+    if let Some(comment) = &header_options.comment {
+        optional.extend_from_slice(comment);
+        optional.push(0);
+    }
+    let trailing_crc_len: u32 = if header_options.header_crc { 2 } else { 0 };
+
     header
-        .write_u32::<LittleEndian>(compressed_size + 28)
+        .write_u32::<LittleEndian>(
+            compressed_size
+                + 28
+                + user_extra.len() as u32
+                + optional.len() as u32
+                + trailing_crc_len,
+        )
         .unwrap(); // Size of block including header and footer
 
+    header.extend_from_slice(user_extra);
+    header.extend(optional);
+
+    if header_options.header_crc {
+        header
+            .write_u16::<LittleEndian>(header_crc16(&header))
+            .unwrap();
+    }
+
     header
 }
 
+/// Compute the FHCRC header CRC16: the low two bytes of the CRC32 of the header bytes written
+/// so far.
+#[inline]
+pub(crate) fn header_crc16(header: &[u8]) -> u16 {
+    #[cfg(feature = "libdeflate")]
+    {
+        let mut crc = libdeflater::Crc::new();
+        crc.update(header);
+        crc.sum() as u16
+    }
+    #[cfg(not(feature = "libdeflate"))]
+    {
+        let mut crc = flate2::Crc::new();
+        crc.update(header);
+        crc.sum() as u16
+    }
+}
+
+/// Parse the RFC 1952 optional fields (FNAME, FCOMMENT, FHCRC) that may follow the mandatory
+/// mgzip extra subfield, returning the number of leading bytes of `buf` they occupy.
+///
+/// `flags` is the FLG byte read from the fixed 20-byte block header.
+///
+/// On a truncated/corrupted `buf` (a NUL-terminated field whose terminator never arrives within
+/// `buf`), returns `buf.len()` rather than indexing out of bounds; the caller ends up treating the
+/// rest of `buf` as this field, which downstream decoding then rejects as corrupt rather than
+/// panicking on it.
+pub(crate) fn skip_optional_fields(flags: u8, buf: &[u8]) -> usize {
+    let mut offset = 0;
+    if flags & FNAME != 0 {
+        offset = match buf[offset..].iter().position(|&b| b == 0) {
+            Some(i) => offset + i + 1,
+            None => return buf.len(),
+        };
+    }
+    if flags & FCOMMENT != 0 {
+        offset = match buf[offset..].iter().position(|&b| b == 0) {
+            Some(i) => offset + i + 1,
+            None => return buf.len(),
+        };
+    }
+    if flags & FHCRC != 0 {
+        offset = (offset + 2).min(buf.len());
+    }
+    offset
+}
+
+/// Number of leading bytes of a block's `remainder` (everything after the fixed 20-byte header)
+/// occupied by this block's FEXTRA user subfields (if `XLEN` exceeds the mandatory `IG` subfield's
+/// 8 bytes) plus any FNAME/FCOMMENT/FHCRC fields, i.e. everything that precedes the deflate
+/// payload. Used by [`crate::deflate::Mgzip`]'s [`BlockFormatSpec::header_extra_len`] impl so
+/// [`ParDecompress`](crate::par::decompress::ParDecompress) can skip straight to the payload.
+///
+/// `header` is the fixed 20-byte block header. `user_extra_len` is clamped to `remainder`'s length
+/// so a corrupted stream (`XLEN` inconsistent with the block size actually read) can't panic on an
+/// out-of-bounds slice here; the resulting garbage is instead caught downstream as a decode error.
+pub(crate) fn header_extra_len(header: &[u8], remainder: &[u8]) -> usize {
+    let xlen = LittleEndian::read_u16(&header[10..12]) as usize;
+    let user_extra_len = xlen.saturating_sub(8).min(remainder.len());
+    user_extra_len + skip_optional_fields(header[3], &remainder[user_extra_len..])
+}
+
 /// Create an mgzip style footer
 #[cfg(not(feature = "libdeflate"))]
 #[inline]
@@ -286,8 +963,14 @@ where
         self.buffer.extend_from_slice(buf);
         if self.buffer.len() >= self.blocksize {
             let b = self.buffer.split_to(self.blocksize).freeze();
-            let compressed = compress(&b[..], &mut self.compressor, self.compression_level)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let compressed = compress_with_header(
+                &b[..],
+                &mut self.compressor,
+                self.compression_level,
+                &self.header_options,
+            )
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.record_block_boundary(b.len(), compressed.len());
             self.writer.write_all(&compressed)?;
         }
         Ok(buf.len())
@@ -297,11 +980,27 @@ where
     fn flush(&mut self) -> std::io::Result<()> {
         let b = self.buffer.split_to(self.buffer.len()).freeze();
         if !b.is_empty() {
-            let compressed = compress(&b[..], &mut self.compressor, self.compression_level)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let compressed = compress_with_header(
+                &b[..],
+                &mut self.compressor,
+                self.compression_level,
+                &self.header_options,
+            )
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.record_block_boundary(b.len(), compressed.len());
             self.writer.write_all(&compressed)?;
         }
-        self.writer.flush()
+        self.writer.flush()?;
+
+        if let Some(mut index_writer) = self.index_writer.take() {
+            index_writer.write_u64::<LittleEndian>(self.index_entries.len() as u64)?;
+            for (c, u) in self.index_entries.drain(..) {
+                index_writer.write_u64::<LittleEndian>(c)?;
+                index_writer.write_u64::<LittleEndian>(u)?;
+            }
+            index_writer.flush()?;
+        }
+        Ok(())
     }
 }
 
@@ -331,31 +1030,12 @@ where
             }
             let after = self.buffer.remaining();
             total_read += before - after;
+            self.uncompressed_pos += (before - after) as u64;
 
             if total_read == buf.len() {
                 break;
             } else if total_read <= buf.len() {
-                let mut header_buf = vec![0; Mgzip::HEADER_SIZE];
-                if let Ok(()) = self.reader.read_exact(&mut header_buf) {
-                    self.format.check_header(&header_buf).unwrap();
-                    let size = self.format.get_block_size(&header_buf).unwrap();
-
-                    self.compressed_buffer.clear();
-                    self.compressed_buffer.resize(size - Mgzip::HEADER_SIZE, 0);
-                    self.reader.read_exact(&mut self.compressed_buffer)?;
-
-                    let check = self.format.get_footer_values(&self.compressed_buffer);
-                    self.buffer.clear();
-                    self.buffer.resize(check.amount as usize, 0);
-
-                    decompress(
-                        &self.compressed_buffer,
-                        &mut self.decompressor,
-                        &mut self.buffer,
-                        check,
-                    )
-                    .unwrap();
-                } else {
+                if !self.read_member()? {
                     break;
                 }
             }
@@ -365,6 +1045,71 @@ where
     }
 }
 
+impl<R> io::Seek for MgzipSyncReader<R>
+where
+    R: Read + io::Seek,
+{
+    /// Seek to an uncompressed position, using (building if necessary) the block index to jump
+    /// directly to the containing block rather than decompressing from the start.
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            io::SeekFrom::Start(offset) => offset,
+            io::SeekFrom::Current(offset) => {
+                (self.uncompressed_pos as i64 + offset).max(0) as u64
+            }
+            io::SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "seeking relative to the end of an mgzip stream is not supported",
+                ))
+            }
+        };
+
+        if self.index.is_empty() {
+            self.build_index()?;
+        }
+
+        let block_idx = match self
+            .index
+            .binary_search_by_key(&target, |entry| entry.uncompressed_offset)
+        {
+            Ok(i) => i,
+            Err(0) => 0,
+            Err(i) => i - 1,
+        };
+        let entry = self.index[block_idx];
+
+        self.reader
+            .seek(io::SeekFrom::Start(entry.compressed_offset))?;
+        let mut header_buf = vec![0; Mgzip::HEADER_SIZE];
+        self.reader.read_exact(&mut header_buf)?;
+        let size = self
+            .format
+            .get_block_size(&header_buf)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        self.compressed_buffer.clear();
+        self.compressed_buffer.resize(size - Mgzip::HEADER_SIZE, 0);
+        self.reader.read_exact(&mut self.compressed_buffer)?;
+
+        let optional_len = skip_optional_fields(header_buf[3], &self.compressed_buffer);
+        let block = self.compressed_buffer.split_off(optional_len);
+
+        let check = self.format.get_footer_values(&block);
+        self.buffer.clear();
+        self.buffer.resize(check.amount as usize, 0);
+        decompress(&block, &mut self.decompressor, &mut self.buffer, check)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        // Advance past the bytes within this block that precede `target`.
+        let intra_block = (target - entry.uncompressed_offset) as usize;
+        self.buffer.advance(intra_block);
+
+        self.uncompressed_pos = target;
+        Ok(target)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::io::{Read, Write};
@@ -409,4 +1154,164 @@ mod test {
         // Assert decompressed output is equal to input
         assert_eq!(input.to_vec(), bytes);
     }
+
+    #[test]
+    fn test_mgzipsync_with_header_options() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"
+        This is a longer test than normal to come up with a bunch of text.
+        We'll read just a few lines at a time.
+        ";
+
+        let header_options = GzHeaderOptions::new()
+            .fname("input.txt")
+            .comment("a test comment")
+            .mtime(12345)
+            .os(3)
+            .header_crc(true);
+
+        // Compress input to output
+        let mut mgzip =
+            MgzipSyncWriter::with_capacity(out_writer, Compression::new(3), BUFSIZE, header_options);
+        mgzip.write_all(input).unwrap();
+        mgzip.flush().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = MgzipSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+
+        // Assert the header metadata round-tripped
+        let header = gz.header().unwrap();
+        assert_eq!(header.fname, Some(b"input.txt".to_vec()));
+        assert_eq!(header.comment, Some(b"a test comment".to_vec()));
+        assert_eq!(header.mtime, 12345);
+        assert_eq!(header.os, 3);
+    }
+
+    #[test]
+    fn test_mgzipsync_with_user_extra_subfield() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input = b"short block";
+
+        // A user-defined extra subfield, written after the mandatory IG subfield.
+        let header_options = GzHeaderOptions::new().extra_subfield(b'X', b'X', b"payload".to_vec());
+
+        let mut mgzip =
+            MgzipSyncWriter::with_capacity(out_writer, Compression::new(3), BUFSIZE, header_options);
+        mgzip.write_all(input).unwrap();
+        mgzip.flush().unwrap();
+
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        let mut gz = MgzipSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input.to_vec(), bytes);
+
+        // The user subfield survives round-trip in `GzHeader::extra`.
+        let header = gz.header().unwrap();
+        assert_eq!(header.extra, b"XX\x07\0payload".to_vec());
+    }
+
+    #[test]
+    fn test_mgzipsync_seek() {
+        use std::io::Seek;
+
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes, long enough to span more than one small block
+        let input: Vec<u8> = (0..1000).map(|i| (i % 256) as u8).collect();
+
+        // Compress with a small blocksize so the stream has more than one block to index: `write`
+        // cuts one 100-byte block from this single call, and `flush` writes the other 900 bytes
+        let mut mgzip =
+            MgzipSyncWriter::with_capacity(out_writer, Compression::new(3), 100, GzHeaderOptions::default());
+        mgzip.write_all(&input).unwrap();
+        mgzip.flush().unwrap();
+
+        // Seek to the middle of the uncompressed stream and read the rest
+        let file = File::open(output_file).unwrap();
+        let mut gz = MgzipSyncReader::new(file);
+        let target = 550u64;
+        gz.seek(std::io::SeekFrom::Start(target)).unwrap();
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        assert_eq!(input[target as usize..].to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_mgzipsync_reads_plain_gzip_member() {
+        // A plain gzip member, with no mgzip `IG` extra subfield, trailing an mgzip block should
+        // be read transparently.
+        let mgzip_part = b"first part, written as an mgzip block";
+        let plain_part = b"second part, a standard gzip member with no IG extra field";
+
+        let mut stream = Vec::new();
+        {
+            let mut mgzip = MgzipSyncWriter::new(&mut stream, Compression::new(3));
+            mgzip.write_all(mgzip_part).unwrap();
+            mgzip.flush().unwrap();
+        }
+        {
+            let mut gz = flate2::write::GzEncoder::new(&mut stream, Compression::new(3));
+            gz.write_all(plain_part).unwrap();
+            gz.finish().unwrap();
+        }
+
+        let mut gz = MgzipSyncReader::new(&stream[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        let mut expected = mgzip_part.to_vec();
+        expected.extend_from_slice(plain_part);
+        assert_eq!(expected, bytes);
+    }
+
+    #[test]
+    fn test_mgzipsync_reads_concatenated_plain_gzip_members() {
+        // A stream made entirely of plain concatenated gzip members, as produced by `gzip` or
+        // `pigz`, with no mgzip framing at all.
+        let parts: [&[u8]; 3] = [b"alpha ", b"beta beta ", b"gamma gamma gamma"];
+
+        let mut stream = Vec::new();
+        for part in &parts {
+            let mut gz = flate2::write::GzEncoder::new(&mut stream, Compression::new(3));
+            gz.write_all(part).unwrap();
+            gz.finish().unwrap();
+        }
+
+        let mut gz = MgzipSyncReader::new(&stream[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        let expected: Vec<u8> = parts.concat();
+        assert_eq!(expected, bytes);
+    }
 }
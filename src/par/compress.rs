@@ -17,18 +17,94 @@
 //! ```
 use std::{
     io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
     thread::JoinHandle,
 };
 
+use byteorder::{LittleEndian, WriteBytesExt};
 use bytes::{Bytes, BytesMut};
 pub use flate2::Compression;
 use flume::{bounded, Receiver, Sender};
 
 use crate::check::Check;
-use crate::{CompressResult, FormatSpec, GzpError, Message, ZWriter, DICT_SIZE};
+use crate::{
+    Backend, BlockFormatSpec, CompressResult, FormatSpec, GzpError, Message, ZWriter, DICT_SIZE,
+};
 
-/// The [`ParCompress`] builder.
+/// A snapshot of cumulative throughput, reported to a [`ParCompressBuilder::with_progress`]
+/// callback by the writer thread after each block is written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProgressEvent {
+    /// Total uncompressed bytes written so far.
+    pub bytes_in: usize,
+    /// Total compressed bytes written so far.
+    pub bytes_out: usize,
+    /// Total number of blocks written so far.
+    pub blocks: u64,
+}
+
+/// A callback invoked by the writer thread with a [`ProgressEvent`] after each block is written.
+type ProgressCallback = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// A cap on the total bytes of queued-but-not-yet-written chunks, shared between the producer
+/// (`write`/`flush_last`) and the writer thread.
+///
+/// This bounds the *total* size of in-flight data, independent of `num_threads` or
+/// `buffer_size`; a fixed-capacity channel of chunks only bounds their *count*, which still lets
+/// outstanding data balloon to `num_threads * 2 * buffer_size` bytes when chunks are large.
 #[derive(Debug)]
+struct MemoryBudget {
+    used: Mutex<usize>,
+    max: usize,
+    condvar: Condvar,
+}
+
+impl MemoryBudget {
+    fn new(max: usize) -> Self {
+        Self {
+            used: Mutex::new(0),
+            max,
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Block until reserving `bytes` would not exceed `max`, then reserve it.
+    ///
+    /// A single chunk larger than `max` is still let through as soon as the budget is empty, so
+    /// an oversized chunk can't deadlock the producer.
+    ///
+    /// If `interrupt` trips while waiting, returns `Err(GzpError::Interrupted)` without reserving
+    /// anything instead of blocking forever: once cancelled, the compressor/writer threads drain
+    /// and exit without ever calling [`MemoryBudget::release`] again, so nothing would otherwise
+    /// wake a producer parked here.
+    fn acquire(&self, bytes: usize, interrupt: Option<&AtomicBool>) -> Result<(), GzpError> {
+        let mut used = self.used.lock().unwrap();
+        while *used > 0 && *used + bytes > self.max {
+            if interrupt.is_some_and(|i| i.load(Ordering::Relaxed)) {
+                return Err(GzpError::Interrupted);
+            }
+            let (guard, _timeout) = self
+                .condvar
+                .wait_timeout(used, std::time::Duration::from_millis(50))
+                .unwrap();
+            used = guard;
+        }
+        *used += bytes;
+        Ok(())
+    }
+
+    /// Release `bytes` previously reserved with [`MemoryBudget::acquire`].
+    fn release(&self, bytes: usize) {
+        let mut used = self.used.lock().unwrap();
+        *used = used.saturating_sub(bytes);
+        self.condvar.notify_all();
+    }
+}
+
+/// The [`ParCompress`] builder.
 pub struct ParCompressBuilder<F>
 where
     F: FormatSpec,
@@ -43,6 +119,21 @@ where
     format: F,
     /// Whether or not to pin threads to specific cpus and what core to start pins at
     pin_threads: Option<usize>,
+    /// An opt-in sink for a companion block index, set via [`ParCompressBuilder::with_index`].
+    index_writer: Option<Box<dyn Write + Send>>,
+    /// A cap on the total bytes of queued-but-not-yet-written chunks, set via
+    /// [`ParCompressBuilder::max_memory`].
+    max_memory: Option<usize>,
+    /// Whether to eagerly spawn the compressor/writer threads, set via
+    /// [`ParCompressBuilder::dedicated_threads`].
+    dedicated_threads: bool,
+    /// A fixed dictionary reused for every block, set via [`ParCompressBuilder::dictionary`],
+    /// overriding the rolling window tail [`FormatSpec::needs_dict`] would otherwise produce.
+    preset_dictionary: Option<Bytes>,
+    /// A cooperative cancellation flag, set via [`ParCompressBuilder::with_interrupt`].
+    interrupt: Option<Arc<AtomicBool>>,
+    /// A progress-reporting hook, set via [`ParCompressBuilder::with_progress`].
+    progress: Option<ProgressCallback>,
 }
 
 impl<F> ParCompressBuilder<F>
@@ -57,6 +148,12 @@ where
             compression_level: Compression::new(3),
             format: F::new(),
             pin_threads: None,
+            index_writer: None,
+            max_memory: None,
+            dedicated_threads: true,
+            preset_dictionary: None,
+            interrupt: None,
+            progress: None,
         }
     }
 
@@ -100,37 +197,182 @@ where
         self
     }
 
+    /// Set the [`format`](ParCompressBuilder.format) instance to use, e.g. to hand a format
+    /// that carries extra state (like a trained dictionary) to every compressor thread.
+    ///
+    /// `ParCompressBuilder::<Bgzf>::new()...format(Bgzf::with_header_options(..))` is this crate's
+    /// block-gzip output mode: [`crate::deflate::Bgzf`] emits independently-decodable BGZF blocks
+    /// (each a complete gzip member with a `BC` extra subfield recording its size) instead of one
+    /// continuous deflate stream, reusing the same rayon/flume runtime as every other format --
+    /// there's no separate "block mode" flag or parallel type, `F` already selects it.
+    pub fn format(mut self, format: F) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Compress every block against the same fixed `dictionary`, instead of the rolling 32 KiB
+    /// window tail that [`FormatSpec::needs_dict`] otherwise produces from the previous block.
+    ///
+    /// Useful for many short, similar records (log lines, JSON rows) where a single block is too
+    /// small to build up a useful window of its own: a dictionary built from representative
+    /// samples wins back much of the ratio that per-block independence otherwise loses.
+    ///
+    /// Only takes effect for formats whose [`FormatSpec::encode`] actually consults the `dict`
+    /// it's handed (`Gzip`, `Zlib` and `RawDeflate` do, gated on the `any_zlib` feature); others,
+    /// including `Mgzip`, `Bgzf` and `Zstd`, ignore it. `Zstd` takes a trained dictionary through
+    /// `Zstd::with_dictionary` instead.
+    ///
+    /// # Errors
+    /// - [`GzpError::DictionarySize`] if `dictionary` is larger than [`DICT_SIZE`], the DEFLATE
+    ///   window a preset dictionary can usefully fill.
+    pub fn dictionary(mut self, dictionary: Bytes) -> Result<Self, GzpError> {
+        if dictionary.len() > DICT_SIZE {
+            return Err(GzpError::DictionarySize(dictionary.len(), DICT_SIZE));
+        }
+        self.preset_dictionary = Some(dictionary);
+        Ok(self)
+    }
+
+    /// Assert that `backend` matches the deflate implementation actually compiled into this
+    /// binary (see [`Backend`]), failing fast with [`GzpError::UnsupportedBackend`] instead of
+    /// silently running whatever was actually linked.
+    ///
+    /// This can't swap backends at runtime -- flate2 links exactly one per binary -- so getting a
+    /// different one means rebuilding gzp with different Cargo features; this setter only makes
+    /// the expectation explicit and checkable.
+    ///
+    /// # Errors
+    /// - [`GzpError::UnsupportedBackend`] if `backend` isn't the one compiled in.
+    pub fn backend(self, backend: Backend) -> Result<Self, GzpError> {
+        let compiled = Backend::compiled();
+        if backend == compiled {
+            Ok(self)
+        } else {
+            Err(GzpError::UnsupportedBackend(backend, compiled))
+        }
+    }
+
+    /// Bound the total bytes of queued-but-not-yet-written chunks to `max_memory`, independent
+    /// of `num_threads` or `buffer_size`.
+    ///
+    /// `write`/`flush_last` block until there is room in the budget before handing a chunk off
+    /// to the compressor threads, and the writer thread frees that chunk's share of the budget
+    /// once it has been written out. Useful for bounding RSS precisely when streaming huge
+    /// inputs through a few large chunks.
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Whether to eagerly spawn the compressor and writer threads when [`from_writer`](Self::from_writer)
+    /// is called. Defaults to `true`.
+    ///
+    /// Set to `false` to skip thread spawn, channel allocation, and the eventual join entirely
+    /// when the whole input turns out to fit in a single `buffer_size` chunk: that chunk is
+    /// compressed and written inline on the calling thread at [`finish`](crate::ZWriter::finish)
+    /// instead. The moment `write` needs a second chunk, it transparently spawns the usual
+    /// multi-threaded machinery and continues from there, so the public [`Write`]/[`ZWriter`]
+    /// behavior is unaffected either way. Worth disabling when many small inputs (e.g. one per
+    /// file) are compressed and the per-call thread/channel setup dominates the actual work.
+    pub fn dedicated_threads(mut self, dedicated_threads: bool) -> Self {
+        self.dedicated_threads = dedicated_threads;
+        self
+    }
+
+    /// Share a cooperative cancellation flag with this [`ParCompress`]'s compressor and writer
+    /// threads.
+    ///
+    /// Once `interrupt` is set to `true`, the next time each thread would otherwise pull a new
+    /// job from its channel it instead drains its channel quietly (so the producer side never
+    /// blocks on a full channel) and returns [`GzpError::Interrupted`], which `finish`/`drop`
+    /// then surface instead of completing normally or hanging. Checked cooperatively, so a thread
+    /// already partway through compressing or writing a block finishes that block first.
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Register a callback invoked by the writer thread with a [`ProgressEvent`] after each block
+    /// is written, reporting cumulative bytes in, bytes out, and block count.
+    ///
+    /// Lets a caller drive a progress bar or compute an achieved compression ratio without
+    /// wrapping the writer themselves.
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(ProgressEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
     /// Create a configured [`ParCompress`] object.
     pub fn from_writer<W: Write + Send + 'static>(self, writer: W) -> ParCompress<F> {
-        let (tx_compressor, rx_compressor) = bounded(self.num_threads * 2);
-        let (tx_writer, rx_writer) = bounded(self.num_threads * 2);
         let buffer_size = self.buffer_size;
-        let comp_level = self.compression_level;
-        let pin_threads = self.pin_threads;
         let format = self.format;
-        let handle = std::thread::spawn(move || {
-            ParCompress::run(
-                &rx_compressor,
-                &rx_writer,
+        let memory_budget = self.max_memory.map(|max| Arc::new(MemoryBudget::new(max)));
+
+        let runtime = if self.dedicated_threads {
+            let (handle, tx_compressor, tx_writer) = ParCompress::spawn(
                 writer,
                 self.num_threads,
-                comp_level,
+                self.compression_level,
                 format,
-                pin_threads,
-            )
-        });
+                self.pin_threads,
+                self.index_writer,
+                memory_budget.clone(),
+                self.interrupt.clone(),
+                self.progress.clone(),
+            );
+            Runtime::Threaded {
+                handle: Some(handle),
+                tx_compressor: Some(tx_compressor),
+                tx_writer: Some(tx_writer),
+            }
+        } else {
+            Runtime::Lazy(Box::new(LazyState {
+                writer: Box::new(writer),
+                compression_level: self.compression_level,
+                index_writer: self.index_writer,
+                num_threads: self.num_threads,
+                pin_threads: self.pin_threads,
+            }))
+        };
+
         ParCompress {
-            handle: Some(handle),
-            tx_compressor: Some(tx_compressor),
-            tx_writer: Some(tx_writer),
+            runtime,
             dictionary: None,
+            preset_dictionary: self.preset_dictionary,
             buffer: BytesMut::with_capacity(buffer_size),
             buffer_size,
             format,
+            memory_budget,
+            interrupt: self.interrupt,
+            progress: self.progress,
+            finished: false,
+            wrote_first_chunk: false,
         }
     }
 }
 
+impl<F> std::fmt::Debug for ParCompressBuilder<F>
+where
+    F: FormatSpec,
+{
+    /// Manual impl since `index_writer` and `progress` are trait objects that aren't `Debug`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParCompressBuilder")
+            .field("buffer_size", &self.buffer_size)
+            .field("num_threads", &self.num_threads)
+            .field("compression_level", &self.compression_level)
+            .field("format", &self.format)
+            .field("pin_threads", &self.pin_threads)
+            .field("max_memory", &self.max_memory)
+            .field("dedicated_threads", &self.dedicated_threads)
+            .field("preset_dictionary", &self.preset_dictionary)
+            .finish_non_exhaustive()
+    }
+}
+
 impl<F> Default for ParCompressBuilder<F>
 where
     F: FormatSpec,
@@ -140,18 +382,92 @@ where
     }
 }
 
+impl<F> ParCompressBuilder<F>
+where
+    F: BlockFormatSpec,
+{
+    /// Opt into writing a companion block index alongside the compressed output.
+    ///
+    /// As the writer thread writes each compressed block, it also records the cumulative
+    /// compressed and uncompressed byte offsets at the start of that block. Once the whole
+    /// stream (including its footer) has been written, the recorded offsets are serialized to
+    /// `index_writer` in one shot, as a `u64` entry count followed by that many
+    /// `(compressed_offset, uncompressed_offset)` pairs of little-endian `u64`s.
+    ///
+    /// This only makes sense for block-oriented formats, where each compressed block can be
+    /// decoded independently: a reader can binary search the index for the block containing a
+    /// target uncompressed coordinate, then decompress forward from that block's start. For
+    /// BGZF specifically, see [`Bgzf::virtual_offset`](crate::deflate::Bgzf::virtual_offset) to
+    /// fold an entry into a single htslib-style virtual offset.
+    ///
+    /// This is the `.gzi`-style random-access index: same little-endian u64-count-then-pairs
+    /// layout, same offset bookkeeping in the writer thread's loop. On the read side,
+    /// [`ParDecompress::load_index`](crate::par::decompress::ParDecompress::load_index) plus its
+    /// [`io::Seek`](std::io::Seek) impl is the reader half of this pair -- a checkpoint list and
+    /// a binary-searching seek, just not under separate `Checkpoint`/`ParSeekReader` names.
+    pub fn with_index<W2: Write + Send + 'static>(mut self, index_writer: W2) -> Self {
+        self.index_writer = Some(Box::new(index_writer));
+        self
+    }
+}
+
+/// Everything a [`Runtime::Lazy`] [`ParCompress`] needs to either finish inline or upgrade to
+/// [`Runtime::Threaded`], whichever happens first.
+struct LazyState {
+    writer: Box<dyn Write + Send>,
+    compression_level: Compression,
+    index_writer: Option<Box<dyn Write + Send>>,
+    num_threads: usize,
+    pin_threads: Option<usize>,
+}
+
+/// An item sent from the producer (`write`/`flush_last`) to the writer thread: how many
+/// uncompressed bytes went in, the oneshot channel the corresponding compressed bytes will
+/// arrive on, and, for [`ParCompress::sync_flush`] only, a oneshot to notify once this job's
+/// bytes have actually been written to the underlying sink.
+type WriterJob<C> = (usize, Receiver<CompressResult<C>>, Option<Sender<()>>);
+
+/// How a [`ParCompress`] is compressing its input, set by
+/// [`ParCompressBuilder::dedicated_threads`].
+enum Runtime<F>
+where
+    F: FormatSpec,
+{
+    /// No chunk has been compressed yet, so the underlying writer is still held directly: if the
+    /// whole input fits in one `buffer_size` chunk, `finish` can compress and write it (and the
+    /// footer) in a single inline pass with no threads or channels at all.
+    ///
+    /// Upgrades to [`Runtime::Threaded`] the moment a second chunk turns out to be needed.
+    Lazy(Box<LazyState>),
+    /// The normal multi-threaded path: dedicated compressor threads and a writer thread,
+    /// coordinated over `flume` channels.
+    Threaded {
+        handle: Option<std::thread::JoinHandle<Result<(), GzpError>>>,
+        tx_compressor: Option<Sender<Message<F::C>>>,
+        tx_writer: Option<Sender<WriterJob<F::C>>>,
+    },
+}
+
 #[allow(unused)]
 pub struct ParCompress<F>
 where
     F: FormatSpec,
 {
-    handle: Option<std::thread::JoinHandle<Result<(), GzpError>>>,
-    tx_compressor: Option<Sender<Message<F::C>>>,
-    tx_writer: Option<Sender<Receiver<CompressResult<F::C>>>>,
+    runtime: Runtime<F>,
     buffer: BytesMut,
     dictionary: Option<Bytes>,
+    /// A fixed dictionary reused for every block, overriding the rolling window tail above.
+    preset_dictionary: Option<Bytes>,
     buffer_size: usize,
     format: F,
+    memory_budget: Option<Arc<MemoryBudget>>,
+    /// A cooperative cancellation flag, set via [`ParCompressBuilder::with_interrupt`].
+    interrupt: Option<Arc<AtomicBool>>,
+    /// A progress-reporting hook, set via [`ParCompressBuilder::with_progress`].
+    progress: Option<ProgressCallback>,
+    finished: bool,
+    /// Whether a `Message` has already been marked `is_first` for this stream.
+    wrote_first_chunk: bool,
 }
 
 impl<F> ParCompress<F>
@@ -163,17 +479,150 @@ where
         ParCompressBuilder::new()
     }
 
+    /// Spawn the compressor and writer threads for the [`Runtime::Threaded`] path.
+    #[allow(clippy::type_complexity)]
+    fn spawn<W: Write + Send + 'static>(
+        writer: W,
+        num_threads: usize,
+        compression_level: Compression,
+        format: F,
+        pin_threads: Option<usize>,
+        index_writer: Option<Box<dyn Write + Send>>,
+        memory_budget: Option<Arc<MemoryBudget>>,
+        interrupt: Option<Arc<AtomicBool>>,
+        progress: Option<ProgressCallback>,
+    ) -> (
+        std::thread::JoinHandle<Result<(), GzpError>>,
+        Sender<Message<F::C>>,
+        Sender<WriterJob<F::C>>,
+    ) {
+        let (tx_compressor, rx_compressor) = bounded(num_threads * 2);
+        let (tx_writer, rx_writer) = bounded(num_threads * 2);
+        let handle = std::thread::spawn(move || {
+            ParCompress::run(
+                &rx_compressor,
+                &rx_writer,
+                writer,
+                num_threads,
+                compression_level,
+                format,
+                pin_threads,
+                index_writer,
+                memory_budget,
+                interrupt,
+                progress,
+            )
+        });
+        (handle, tx_compressor, tx_writer)
+    }
+
+    /// Upgrade from [`Runtime::Lazy`] to [`Runtime::Threaded`] if not already threaded, since a
+    /// second chunk means there's real work to parallelize.
+    fn ensure_threaded(&mut self) -> io::Result<()> {
+        if matches!(self.runtime, Runtime::Threaded { .. }) {
+            return Ok(());
+        }
+        let placeholder = Runtime::Threaded {
+            handle: None,
+            tx_compressor: None,
+            tx_writer: None,
+        };
+        let lazy = match std::mem::replace(&mut self.runtime, placeholder) {
+            Runtime::Lazy(state) => state,
+            Runtime::Threaded { .. } => unreachable!("checked above"),
+        };
+        let (handle, tx_compressor, tx_writer) = Self::spawn(
+            lazy.writer,
+            lazy.num_threads,
+            lazy.compression_level,
+            self.format,
+            lazy.pin_threads,
+            lazy.index_writer,
+            self.memory_budget.clone(),
+            self.interrupt.clone(),
+            self.progress.clone(),
+        );
+        self.runtime = Runtime::Threaded {
+            handle: Some(handle),
+            tx_compressor: Some(tx_compressor),
+            tx_writer: Some(tx_writer),
+        };
+        Ok(())
+    }
+
+    /// Compress and write the single chunk buffered so far, plus the footer, inline on the
+    /// calling thread, with no threads or channels at all.
+    ///
+    /// Only reachable from `finish` while still [`Runtime::Lazy`], i.e. when the whole input
+    /// turned out to fit in one `buffer_size` chunk.
+    fn finish_inline(&mut self) -> io::Result<()> {
+        let placeholder = Runtime::Threaded {
+            handle: None,
+            tx_compressor: None,
+            tx_writer: None,
+        };
+        let lazy = match std::mem::replace(&mut self.runtime, placeholder) {
+            Runtime::Lazy(state) => state,
+            Runtime::Threaded { .. } => unreachable!("finish_inline only called while Lazy"),
+        };
+        let LazyState {
+            mut writer,
+            compression_level,
+            index_writer,
+            ..
+        } = *lazy;
+
+        let input = self.buffer.split_to(self.buffer.len()).freeze();
+        let mut compressor = self
+            .format
+            .create_compressor(compression_level)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let encoded = self
+            .format
+            .encode(
+                &input,
+                &mut compressor,
+                compression_level,
+                self.preset_dictionary.as_ref(),
+                true,
+                true,
+            )
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let mut check = F::create_check();
+        check.update(&input);
+
+        let header = self.format.header(compression_level);
+        writer.write_all(&header)?;
+        writer.write_all(&encoded)?;
+        writer.write_all(&self.format.footer(&check))?;
+        writer.flush()?;
+
+        if let Some(mut index_writer) = index_writer {
+            // A single entry: the one block starts right after the header.
+            index_writer.write_u64::<LittleEndian>(1)?;
+            index_writer.write_u64::<LittleEndian>(header.len() as u64)?;
+            index_writer.write_u64::<LittleEndian>(0)?;
+            index_writer.flush()?;
+        }
+
+        Ok(())
+    }
+
     /// Launch threads to compress chunks and coordinate sending compressed results
     /// to the writer.
     #[allow(clippy::needless_collect)]
     fn run<W>(
         rx: &Receiver<Message<F::C>>,
-        rx_writer: &Receiver<Receiver<CompressResult<F::C>>>,
+        rx_writer: &Receiver<WriterJob<F::C>>,
         mut writer: W,
         num_threads: usize,
         compression_level: Compression,
         format: F,
         pin_threads: Option<usize>,
+        index_writer: Option<Box<dyn Write + Send>>,
+        memory_budget: Option<Arc<MemoryBudget>>,
+        interrupt: Option<Arc<AtomicBool>>,
+        progress: Option<ProgressCallback>,
     ) -> Result<(), GzpError>
     where
         W: Write + Send + 'static,
@@ -183,6 +632,7 @@ where
             .map(|i| {
                 let rx = rx.clone();
                 let core_ids = core_ids.clone();
+                let interrupt = interrupt.clone();
                 std::thread::spawn(move || -> Result<(), GzpError> {
                     if let Some(pin_at) = pin_threads {
                         if let Some(id) = core_ids.get(pin_at + i) {
@@ -192,12 +642,21 @@ where
 
                     let mut compressor = format.create_compressor(compression_level)?;
                     while let Ok(m) = rx.recv() {
+                        if let Some(interrupt) = &interrupt {
+                            if interrupt.load(Ordering::Relaxed) {
+                                // Drain quietly so the producer never blocks on a full channel,
+                                // without compressing anything further.
+                                while rx.recv().is_ok() {}
+                                return Err(GzpError::Interrupted);
+                            }
+                        }
                         let chunk = &m.buffer;
                         let buffer = format.encode(
                             chunk,
                             &mut compressor,
                             compression_level,
                             m.dictionary.as_ref(),
+                            m.is_first,
                             m.is_last,
                         )?;
                         let mut check = F::create_check();
@@ -215,18 +674,70 @@ where
             .collect();
 
         // Writer
-        writer.write_all(&format.header(compression_level))?;
+        let header = format.header(compression_level);
+        writer.write_all(&header)?;
         let mut running_check = F::create_check();
-        while let Ok(chunk_chan) = rx_writer.recv() {
+        // Cumulative offsets for the optional block index, tracked only on this thread so they
+        // stay correct regardless of the order in which compressor threads finish.
+        let mut compressed_offset = header.len() as u64;
+        let mut uncompressed_offset = 0u64;
+        let mut index_entries = Vec::new();
+        let mut total_bytes_in = 0usize;
+        let mut total_bytes_out = 0usize;
+        let mut blocks_written = 0u64;
+        while let Ok((uncompressed_len, chunk_chan, ack)) = rx_writer.recv() {
+            if let Some(interrupt) = &interrupt {
+                if interrupt.load(Ordering::Relaxed) {
+                    // Drain quietly so the producer never blocks on a full channel, without
+                    // writing anything further.
+                    while rx_writer.recv().is_ok() {}
+                    return Err(GzpError::Interrupted);
+                }
+            }
             let chunk_chan: Receiver<CompressResult<F::C>> = chunk_chan;
             let (check, chunk) = chunk_chan.recv()??;
             running_check.combine(&check);
+            if index_writer.is_some() {
+                index_entries.push((compressed_offset, uncompressed_offset));
+                uncompressed_offset += uncompressed_len as u64;
+            }
+            compressed_offset += chunk.len() as u64;
             writer.write_all(&chunk)?;
+            if let Some(budget) = &memory_budget {
+                budget.release(uncompressed_len);
+            }
+            if let Some(progress) = &progress {
+                total_bytes_in += uncompressed_len;
+                total_bytes_out += chunk.len();
+                blocks_written += 1;
+                progress(ProgressEvent {
+                    bytes_in: total_bytes_in,
+                    bytes_out: total_bytes_out,
+                    blocks: blocks_written,
+                });
+            }
+            // Since this thread is the sole writer and processes jobs strictly in submission
+            // order, acking here tells `sync_flush` that every byte submitted up to and
+            // including this job has actually reached the underlying writer.
+            if let Some(ack) = ack {
+                let _ = ack.send(());
+            }
         }
         let footer = format.footer(&running_check);
         writer.write_all(&footer)?;
         writer.flush()?;
 
+        // Write the index only after the compressed stream (including its footer) is fully
+        // written, so a reader never observes an index pointing past the end of the data.
+        if let Some(mut index_writer) = index_writer {
+            index_writer.write_u64::<LittleEndian>(index_entries.len() as u64)?;
+            for (c, u) in index_entries {
+                index_writer.write_u64::<LittleEndian>(c)?;
+                index_writer.write_u64::<LittleEndian>(u)?;
+            }
+            index_writer.flush()?;
+        }
+
         // Gracefully shutdown the compression threads
         handles
             .into_iter()
@@ -244,26 +755,72 @@ where
     /// # Panics
     /// - If called after `finish`
     fn flush_last(&mut self, is_last: bool) -> std::io::Result<()> {
+        self.flush_last_with_ack(is_last, None)
+    }
+
+    /// As [`Self::flush_last`], but if `ack` is set it is attached to the final job submitted
+    /// (the one that leaves `self.buffer` empty), so its sender fires once the writer thread has
+    /// actually written that job's bytes. Used by [`Self::sync_flush`] to block on that signal.
+    fn flush_last_with_ack(
+        &mut self,
+        is_last: bool,
+        mut ack: Option<Sender<()>>,
+    ) -> std::io::Result<()> {
+        if is_last && matches!(self.runtime, Runtime::Lazy(_)) {
+            return self.finish_inline();
+        }
+        self.ensure_threaded()?;
         loop {
             let b = self
                 .buffer
                 .split_to(std::cmp::min(self.buffer.len(), self.buffer_size))
                 .freeze();
-            let (mut m, r) = Message::new_parts(b, std::mem::replace(&mut self.dictionary, None));
+            let uncompressed_len = b.len();
+            let dict = self
+                .preset_dictionary
+                .clone()
+                .or_else(|| std::mem::replace(&mut self.dictionary, None));
+            let (mut m, r) = Message::new_parts(b, dict);
+            if !self.wrote_first_chunk {
+                m.is_first = true;
+                self.wrote_first_chunk = true;
+            }
             if is_last && self.buffer.is_empty() {
                 m.is_last = true;
             }
 
-            if m.buffer.len() >= DICT_SIZE && !m.is_last && self.format.needs_dict() {
+            if self.preset_dictionary.is_none()
+                && m.buffer.len() >= DICT_SIZE
+                && !m.is_last
+                && self.format.needs_dict()
+            {
                 self.dictionary = Some(m.buffer.slice(m.buffer.len() - DICT_SIZE..));
             }
 
-            self.tx_writer
+            if let Some(budget) = &self.memory_budget {
+                budget
+                    .acquire(uncompressed_len, self.interrupt.as_deref())
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            }
+            let (tx_compressor, tx_writer) = match &self.runtime {
+                Runtime::Threaded {
+                    tx_compressor,
+                    tx_writer,
+                    ..
+                } => (tx_compressor, tx_writer),
+                Runtime::Lazy(_) => unreachable!("ensure_threaded was just called"),
+            };
+            let job_ack = if self.buffer.is_empty() {
+                ack.take()
+            } else {
+                None
+            };
+            tx_writer
                 .as_ref()
                 .unwrap()
-                .send(r)
+                .send((uncompressed_len, r, job_ack))
                 .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-            self.tx_compressor
+            tx_compressor
                 .as_ref()
                 .unwrap()
                 .send(m)
@@ -292,14 +849,48 @@ where
     fn finish(&mut self) -> Result<(), GzpError> {
         self.flush_last(true)?;
 
-        // while !self.tx_compressor.as_ref().unwrap().is_empty() {}
-        // while !self.tx_writer.as_ref().unwrap().is_empty() {}
-        drop(self.tx_compressor.take());
-        drop(self.tx_writer.take());
-        match self.handle.take().unwrap().join() {
-            Ok(result) => result,
-            Err(e) => std::panic::resume_unwind(e),
-        }
+        let result = match &mut self.runtime {
+            Runtime::Threaded {
+                handle,
+                tx_compressor,
+                tx_writer,
+            } => {
+                drop(tx_compressor.take());
+                drop(tx_writer.take());
+                match handle.take() {
+                    Some(handle) => match handle.join() {
+                        Ok(result) => result,
+                        Err(e) => std::panic::resume_unwind(e),
+                    },
+                    // `finish_inline` already did the work and left this placeholder behind.
+                    None => Ok(()),
+                }
+            }
+            Runtime::Lazy(_) => unreachable!("flush_last(true) always leaves Runtime::Threaded"),
+        };
+        self.finished = true;
+        result
+    }
+
+    /// Force a checkpoint: every byte written so far is handed to the underlying writer, in
+    /// order, forming a decodable prefix (each block's `FormatSpec::encode` already uses a
+    /// `Z_SYNC_FLUSH`-equivalent flush when `is_last` is false), while the compressor and
+    /// dictionary state are left untouched so the stream continues normally afterwards.
+    ///
+    /// `flush` alone only guarantees the buffered bytes have been *handed off* to the compressor
+    /// and writer threads over their channels; it does not wait for the writer thread to have
+    /// actually written them. This submits the buffered bytes (an empty job if nothing is
+    /// buffered) with a oneshot ack attached to the last job, and blocks on that ack, which works because the writer thread processes jobs strictly in submission order, so
+    /// the ack firing implies every prior job's bytes already reached the underlying writer.
+    ///
+    /// # Errors
+    /// - [`GzpError`] if submitting the flush job fails or the writer thread's ack channel is
+    ///   dropped, which only happens if the writer thread has already died with an error.
+    fn sync_flush(&mut self) -> Result<(), GzpError> {
+        let (tx_ack, rx_ack) = bounded(1);
+        self.flush_last_with_ack(false, Some(tx_ack))?;
+        rx_ack.recv()?;
+        Ok(())
     }
 }
 
@@ -308,10 +899,22 @@ where
     F: FormatSpec,
 {
     fn drop(&mut self) {
-        if self.tx_compressor.is_some() && self.tx_writer.is_some() && self.handle.is_some() {
+        if !self.finished {
             self.finish().unwrap();
         }
-        // Resources already cleaned up if channels and handle are None
+    }
+}
+
+impl<F> ParCompress<F>
+where
+    F: FormatSpec,
+{
+    /// Take the writer thread's handle out of [`Runtime::Threaded`], if present.
+    fn take_handle(&mut self) -> Option<std::thread::JoinHandle<Result<(), GzpError>>> {
+        match &mut self.runtime {
+            Runtime::Threaded { handle, .. } => handle.take(),
+            Runtime::Lazy(_) => None,
+        }
     }
 }
 
@@ -326,39 +929,43 @@ where
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.buffer.extend_from_slice(buf);
         while self.buffer.len() > self.buffer_size {
+            self.ensure_threaded()?;
             let b = self.buffer.split_to(self.buffer_size).freeze();
-            let (m, r) = Message::new_parts(b, std::mem::replace(&mut self.dictionary, None));
+            let uncompressed_len = b.len();
+            let dict = self
+                .preset_dictionary
+                .clone()
+                .or_else(|| std::mem::replace(&mut self.dictionary, None));
+            let (mut m, r) = Message::new_parts(b, dict);
+            if !self.wrote_first_chunk {
+                m.is_first = true;
+                self.wrote_first_chunk = true;
+            }
             // Bytes uses and ARC, this is O(1) to get the last 32k bytes from teh previous chunk
-            self.dictionary = if self.format.needs_dict() {
+            self.dictionary = if self.preset_dictionary.is_none() && self.format.needs_dict() {
                 Some(m.buffer.slice(m.buffer.len() - DICT_SIZE..))
             } else {
                 None
             };
-            self.tx_writer
-                .as_ref()
-                .unwrap()
-                .send(r)
-                .map_err(|_send_error| {
-                    // If an error occured sending, that means the recievers have dropped an the compressor thread hit an error
-                    // Collect that error here, and if it was an Io error, preserve it
-                    let error = match self.handle.take().unwrap().join() {
-                        Ok(result) => result,
-                        Err(e) => std::panic::resume_unwind(e),
-                    };
-                    match error {
-                        Ok(()) => std::panic::resume_unwind(Box::new(error)), // something weird happened
-                        Err(GzpError::Io(ioerr)) => ioerr,
-                        Err(err) => io::Error::new(io::ErrorKind::Other, err),
-                    }
-                })?;
-            self.tx_compressor
-                .as_ref()
-                .unwrap()
-                .send(m)
+            if let Some(budget) = &self.memory_budget {
+                budget
+                    .acquire(uncompressed_len, self.interrupt.as_deref())
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            }
+            let (tx_compressor, tx_writer) = match &self.runtime {
+                Runtime::Threaded {
+                    tx_compressor,
+                    tx_writer,
+                    ..
+                } => (tx_compressor.clone().unwrap(), tx_writer.clone().unwrap()),
+                Runtime::Lazy(_) => unreachable!("ensure_threaded was just called"),
+            };
+            tx_writer
+                .send((uncompressed_len, r, None))
                 .map_err(|_send_error| {
                     // If an error occured sending, that means the recievers have dropped an the compressor thread hit an error
                     // Collect that error here, and if it was an Io error, preserve it
-                    let error = match self.handle.take().unwrap().join() {
+                    let error = match self.take_handle().unwrap().join() {
                         Ok(result) => result,
                         Err(e) => std::panic::resume_unwind(e),
                     };
@@ -368,6 +975,19 @@ where
                         Err(err) => io::Error::new(io::ErrorKind::Other, err),
                     }
                 })?;
+            tx_compressor.send(m).map_err(|_send_error| {
+                // If an error occured sending, that means the recievers have dropped an the compressor thread hit an error
+                // Collect that error here, and if it was an Io error, preserve it
+                let error = match self.take_handle().unwrap().join() {
+                    Ok(result) => result,
+                    Err(e) => std::panic::resume_unwind(e),
+                };
+                match error {
+                    Ok(()) => std::panic::resume_unwind(Box::new(error)), // something weird happened
+                    Err(GzpError::Io(ioerr)) => ioerr,
+                    Err(err) => io::Error::new(io::ErrorKind::Other, err),
+                }
+            })?;
             self.buffer
                 .reserve(self.buffer_size.saturating_sub(self.buffer.len()));
         }
@@ -1,17 +1,107 @@
-//! Parallel decompression for block type gzip formats (mgzip, bgzf)
+//! Parallel decompression for block type gzip formats (mgzip, bgzf), plus (behind the `deflate`
+//! feature) [`GzipParDecompress`] for ordinary, non-block-framed concatenated gzip, and (behind
+//! the `snappy` feature) [`ParSnapDecompress`] for the Snappy frame format.
+//!
+//! [`ParDecompress`] is this module's coordinator-thread-plus-worker-pool reader: it reads input
+//! on one thread, detects each block's extent from its header (for BGZF, the BSIZE subfield,
+//! without inflating), dispatches each block to a worker pool for `flate2` inflation, and
+//! reassembles output in original order via per-block sequence numbers, verifying each block's
+//! check value along the way. [`ParDecompressBuilder::maybe_par_from_reader`] falls back to a
+//! serial [`MultiGzDecoder`] when `num_threads` is 0.
 
 use std::{
-    io::{self, Read},
+    io::{self, BufRead, Read, Seek},
+    sync::{Arc, Mutex},
     thread::JoinHandle,
 };
 
-use bytes::{BufMut, Bytes, BytesMut};
+use byteorder::{ByteOrder, LittleEndian};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+#[cfg(feature = "deflate")]
+use flate2::{Decompress, FlushDecompress, Status};
+#[cfg(feature = "deflate")]
+use flate2::bufread::DeflateDecoder;
 use flate2::read::MultiGzDecoder;
 pub use flate2::Compression;
 use flume::{bounded, unbounded, Receiver, Sender};
+#[cfg(feature = "snappy")]
+use snap::raw::Decoder as SnapDecoder;
 
+#[cfg(feature = "deflate")]
+use crate::check::Crc32;
+#[cfg(feature = "snappy")]
+use crate::check::Crc32c;
+#[cfg(feature = "any_zlib")]
+use crate::check::Adler32;
 use crate::{BlockFormatSpec, Check, GzpError, BUFSIZE, DICT_SIZE};
 
+/// Default cap on a single block's declared uncompressed-header size, used by
+/// [`ParDecompressBuilder::max_block_size`]. Chosen to comfortably fit any legitimate block
+/// produced by this crate's writers (which default to [`BUFSIZE`]-ish blocks) while still
+/// rejecting a corrupt or hostile header that claims a multi-gigabyte block before a single byte
+/// of it has been validated.
+pub const DEFAULT_MAX_BLOCK_SIZE: usize = 512 * (1 << 20);
+
+/// Blanket alias for a seekable reader, used so [`ParDecompress`] can hold one behind a `Box`
+/// (via [`Pending`]) without adding a second generic parameter to its own type.
+trait ReadSeek: Read + io::Seek {}
+impl<T: Read + io::Seek> ReadSeek for T {}
+
+/// How [`ParDecompress`] should react to a damaged or truncated block rather than always
+/// aborting the whole stream. Set via [`ParDecompressBuilder::on_error`]; defaults to
+/// [`OnError::Fail`], the only behavior before this existed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnError {
+    /// Abort on the first bad header, short read, failed inflate, or check mismatch.
+    #[default]
+    Fail,
+    /// A block whose header parses but whose body then fails to inflate or fails its check is
+    /// recorded in [`ParDecompress::errors`] and replaced with an empty result; reading
+    /// continues with the next block. A header that itself fails to parse, or a short read that
+    /// truncates a block's body, can't be skipped this way (there's no way to know where the
+    /// next block starts without it), so those fall back to [`OnError::Truncate`]'s behavior.
+    SkipBlock,
+    /// Stop at the first bad header, short read, failed inflate, or check mismatch, as if the
+    /// stream had cleanly ended right there, recording the failure in [`ParDecompress::errors`]
+    /// instead of returning an error to the caller.
+    Truncate,
+}
+
+/// A block [`ParDecompress`] skipped or stopped at under a non-[`OnError::Fail`] recovery mode,
+/// recorded so a caller can inspect what was lost after reading to completion.
+#[derive(Debug, Clone)]
+pub struct SkippedBlock {
+    /// Byte offset into the compressed input where this block's header began.
+    pub offset: u64,
+    /// What went wrong, as the underlying error's `Display` text (kept as a string since
+    /// [`GzpError`] isn't [`Clone`]).
+    pub error: String,
+}
+
+/// A single entry in a GZI-style block index, as written by
+/// [`ParCompressBuilder::with_index`](crate::par::compress::ParCompressBuilder::with_index) and
+/// loaded by [`ParDecompress::load_index`].
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    compressed_offset: u64,
+    uncompressed_offset: u64,
+}
+
+/// A [`ParDecompress`] built by
+/// [`ParDecompressBuilder::from_seekable_reader`] holds onto its reader directly in here, rather
+/// than handing it to the background reader thread, until the first [`Read::read`] or
+/// [`io::Seek::seek`] call needs it: this is the one window in which seeking is possible, since
+/// once the reader has been moved into the thread it can't be recovered to seek directly.
+struct Pending {
+    reader: Box<dyn ReadSeek + Send>,
+    num_threads: usize,
+    pin_threads: Option<usize>,
+    max_block_size: usize,
+    multi_member: bool,
+    verify: bool,
+    on_error: OnError,
+}
+
 #[derive(Debug)]
 pub struct ParDecompressBuilder<F>
 where
@@ -21,6 +111,10 @@ where
     num_threads: usize,
     format: F,
     pin_threads: Option<usize>,
+    max_block_size: usize,
+    multi_member: bool,
+    verify: bool,
+    on_error: OnError,
 }
 
 impl<F> ParDecompressBuilder<F>
@@ -33,6 +127,10 @@ where
             num_threads: num_cpus::get(),
             format: F::new(),
             pin_threads: None,
+            max_block_size: DEFAULT_MAX_BLOCK_SIZE,
+            multi_member: true,
+            verify: true,
+            on_error: OnError::Fail,
         }
     }
 
@@ -44,6 +142,13 @@ where
         Ok(self)
     }
 
+    /// Set the [`format`](ParDecompressBuilder.format) instance to use, e.g. to hand a format
+    /// that carries extra state (like a trained dictionary) to every decompressor thread.
+    pub fn format(mut self, format: F) -> Self {
+        self.format = format;
+        self
+    }
+
     /// Set the number of threads and verify that that they are > 0 ensuring the mulit-threaded decompression will be attempted.
     pub fn num_threads(mut self, num_threads: usize) -> Result<Self, GzpError> {
         if num_threads == 0 {
@@ -59,14 +164,70 @@ where
         self
     }
 
+    /// Set the maximum uncompressed block size a block header is allowed to declare, guarding
+    /// against a corrupt or hostile header claiming a huge block before any of its bytes have
+    /// been validated. Defaults to [`DEFAULT_MAX_BLOCK_SIZE`].
+    pub fn max_block_size(mut self, max_block_size: usize) -> Self {
+        self.max_block_size = max_block_size;
+        self
+    }
+
+    /// Whether to keep decompressing past the format's first end-of-stream marker (e.g. BGZF's
+    /// empty EOF block) if more blocks follow, as if they were a single concatenated stream.
+    /// Defaults to `true`. Set to `false` to stop right after the first member instead, leaving
+    /// any trailing bytes (which may belong to an outer container rather than another member)
+    /// unread rather than erroring out on them.
+    ///
+    /// Formats with no explicit end-of-stream marker (i.e. whose
+    /// [`BlockFormatSpec::is_eof_marker`] is never `true`) are unaffected by this setting: they
+    /// always stop at the first clean EOF regardless.
+    pub fn multi_member(mut self, multi_member: bool) -> Self {
+        self.multi_member = multi_member;
+        self
+    }
+
+    /// Whether to recompute each block's CRC32 (and check its decompressed length) against the
+    /// value stored in its trailer after inflating. Defaults to `true`. Set to `false` for a fast
+    /// lenient mode that skips the recompute for maximum throughput, trusting the compressed
+    /// stream instead of verifying it.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Set how to react to a damaged or truncated block instead of always aborting the whole
+    /// stream. Defaults to [`OnError::Fail`]. See [`ParDecompress::errors`] for how to inspect
+    /// what a non-`Fail` mode skipped or stopped at.
+    pub fn on_error(mut self, on_error: OnError) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
     /// Build a guaranteed multi-threaded decompressor
     pub fn from_reader<R: Read + Send + 'static>(self, reader: R) -> ParDecompress<F> {
         let (tx_reader, rx_reader) = bounded(self.num_threads * 2);
         let buffer_size = self.buffer_size;
         let format = self.format;
         let pin_threads = self.pin_threads;
+        let max_block_size = self.max_block_size;
+        let multi_member = self.multi_member;
+        let verify = self.verify;
+        let on_error = self.on_error;
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let errors_thread = Arc::clone(&errors);
         let handle = std::thread::spawn(move || {
-            ParDecompress::run(&tx_reader, reader, self.num_threads, format, pin_threads)
+            ParDecompress::run(
+                &tx_reader,
+                reader,
+                self.num_threads,
+                format,
+                pin_threads,
+                max_block_size,
+                multi_member,
+                verify,
+                on_error,
+                errors_thread,
+            )
         });
         ParDecompress {
             handle: Some(handle),
@@ -74,6 +235,38 @@ where
             buffer: BytesMut::new(),
             buffer_size,
             format,
+            pending: None,
+            index: Vec::new(),
+            pending_skip: 0,
+            errors,
+        }
+    }
+
+    /// Build a decompressor that defers starting the background reader thread until its first
+    /// read or seek, so a [`ParDecompress::load_index`]-ed [`io::Seek::seek`] call beforehand can
+    /// jump `reader` to the target block before any decompression work begins.
+    pub fn from_seekable_reader<R: Read + io::Seek + Send + 'static>(
+        self,
+        reader: R,
+    ) -> ParDecompress<F> {
+        ParDecompress {
+            handle: None,
+            rx_reader: None,
+            buffer: BytesMut::new(),
+            buffer_size: self.buffer_size,
+            format: self.format,
+            pending: Some(Pending {
+                reader: Box::new(reader),
+                num_threads: self.num_threads,
+                pin_threads: self.pin_threads,
+                max_block_size: self.max_block_size,
+                multi_member: self.multi_member,
+                verify: self.verify,
+                on_error: self.on_error,
+            }),
+            index: Vec::new(),
+            pending_skip: 0,
+            errors: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -112,6 +305,18 @@ where
     buffer: BytesMut,
     buffer_size: usize,
     format: F,
+    /// Set by [`ParDecompressBuilder::from_seekable_reader`]; holds the reader directly until
+    /// the background thread needs to be spawned, so [`io::Seek::seek`] has a window to jump it.
+    pending: Option<Pending>,
+    /// Loaded by [`ParDecompress::load_index`]; empty means seeking is unsupported.
+    index: Vec<IndexEntry>,
+    /// Bytes to discard from the start of the next block handed out by the channel pipeline,
+    /// left over from [`io::Seek::seek`] landing partway into that block.
+    pending_skip: usize,
+    /// Blocks skipped or truncated at under a non-[`OnError::Fail`] recovery mode. Shared with
+    /// the background reader/worker threads so they can record failures as they happen; read
+    /// back via [`ParDecompress::errors`].
+    errors: Arc<Mutex<Vec<SkippedBlock>>>,
 }
 
 impl<F> ParDecompress<F>
@@ -122,17 +327,137 @@ where
         ParDecompressBuilder::new()
     }
 
+    /// Load a GZI-style block index, as written by
+    /// [`ParCompressBuilder::with_index`](crate::par::compress::ParCompressBuilder::with_index):
+    /// a little-endian `u64` entry count followed by that many `(compressed_offset,
+    /// uncompressed_offset)` pairs of little-endian `u64`s.
+    ///
+    /// Only useful on a [`ParDecompress`] built via
+    /// [`ParDecompressBuilder::from_seekable_reader`]; it's what makes [`io::Seek::seek`] able to
+    /// jump to the block containing a target uncompressed offset.
+    pub fn load_index<IR: Read>(&mut self, mut index_reader: IR) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        index_reader.read_to_end(&mut bytes)?;
+        if bytes.len() < 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "index shorter than its entry count",
+            ));
+        }
+        let num_entries = LittleEndian::read_u64(&bytes[..8]) as usize;
+        let mut index = Vec::with_capacity(num_entries);
+        let mut offset = 8;
+        for _ in 0..num_entries {
+            if offset + 16 > bytes.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "index truncated before its declared entry count",
+                ));
+            }
+            index.push(IndexEntry {
+                compressed_offset: LittleEndian::read_u64(&bytes[offset..]),
+                uncompressed_offset: LittleEndian::read_u64(&bytes[offset + 8..]),
+            });
+            offset += 16;
+        }
+        self.index = index;
+        Ok(())
+    }
+
+    /// Seek directly to a BGZF virtual file offset, as used by e.g. BAI/CSI/tabix indexes: the
+    /// high 48 bits are the compressed offset of the block's first byte, and the low 16 bits are
+    /// a byte offset within that block's uncompressed data.
+    ///
+    /// Unlike [`io::Seek::seek`], this needs no [`ParDecompress::load_index`] call first, since
+    /// the virtual offset already pins down the block directly. The same restriction applies
+    /// though: only possible before the background reader thread has started, i.e. on a
+    /// [`ParDecompress`] built via [`ParDecompressBuilder::from_seekable_reader`] that hasn't had
+    /// a [`Read::read`] or [`io::Seek::seek`] call yet.
+    pub fn seek_virtual(&mut self, voffset: u64) -> io::Result<u64> {
+        let pending = match &mut self.pending {
+            Some(pending) => pending,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "cannot seek a ParDecompress once it has started reading",
+                ))
+            }
+        };
+        let compressed_offset = voffset >> 16;
+        let offset_within_block = voffset & 0xffff;
+        pending
+            .reader
+            .seek(io::SeekFrom::Start(compressed_offset))?;
+        self.pending_skip = offset_within_block as usize;
+        Ok(voffset)
+    }
+
+    /// Seek to an uncompressed byte offset, using the index loaded by
+    /// [`ParDecompress::load_index`] to jump the underlying reader directly to the block
+    /// containing it.
+    ///
+    /// A convenience wrapper around [`io::Seek::seek`] (`SeekFrom::Start(offset)`) that spares a
+    /// caller who only wants to seek by plain uncompressed offset from importing [`io::Seek`] and
+    /// constructing a [`io::SeekFrom`], mirroring [`ParDecompress::seek_virtual`]'s shape for the
+    /// htslib-style virtual-offset case. Subject to the same restriction: only possible before the
+    /// background reader thread has started.
+    pub fn seek_uncompressed(&mut self, offset: u64) -> io::Result<u64> {
+        io::Seek::seek(self, io::SeekFrom::Start(offset))
+    }
+
+    /// The blocks skipped or truncated at so far under a non-[`OnError::Fail`] recovery mode, in
+    /// the order they were encountered. Always empty under the default [`OnError::Fail`], since
+    /// that mode errors out instead of recording anything here. Safe to call at any point, but
+    /// only complete once reading has finished (or [`ParDecompress::finish`] has been called).
+    pub fn errors(&self) -> Vec<SkippedBlock> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    /// Spawn the background reader/decompressor threads from [`Pending`] state, if not already
+    /// running. A no-op once the threads have been spawned, e.g. by a prior read or seek.
+    fn ensure_started(&mut self) {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        let (tx_reader, rx_reader) = bounded(pending.num_threads * 2);
+        let format = self.format;
+        let errors = Arc::clone(&self.errors);
+        let handle = std::thread::spawn(move || {
+            ParDecompress::run(
+                &tx_reader,
+                pending.reader,
+                pending.num_threads,
+                format,
+                pending.pin_threads,
+                pending.max_block_size,
+                pending.multi_member,
+                pending.verify,
+                pending.on_error,
+                errors,
+            )
+        });
+        self.handle = Some(handle);
+        self.rx_reader = Some(rx_reader);
+    }
+
     #[allow(clippy::needless_collect)]
     fn run<R>(
         tx_reader: &Sender<Receiver<BytesMut>>,
-        mut reader: R,
+        reader: R,
         num_threads: usize,
         format: F,
         pin_threads: Option<usize>,
+        max_block_size: usize,
+        multi_member: bool,
+        verify: bool,
+        on_error: OnError,
+        errors: Arc<Mutex<Vec<SkippedBlock>>>,
     ) -> Result<(), GzpError>
     where
         R: Read + Send + 'static,
     {
+        let mut reader = io::BufReader::with_capacity(BUFSIZE, reader);
         let (tx, rx): (Sender<DMessage>, Receiver<DMessage>) = bounded(num_threads * 2);
 
         let core_ids = core_affinity::get_core_ids().unwrap();
@@ -140,6 +465,7 @@ where
             .map(|i| {
                 let rx = rx.clone();
                 let core_ids = core_ids.clone();
+                let errors = Arc::clone(&errors);
                 std::thread::spawn(move || -> Result<(), GzpError> {
                     if let Some(pin_at) = pin_threads {
                         if let Some(id) = core_ids.get(pin_at + i) {
@@ -148,25 +474,67 @@ where
                     }
                     let mut decompressor = format.create_decompressor();
                     while let Ok(m) = rx.recv() {
+                        macro_rules! recover_or_fail {
+                            ($err:expr) => {{
+                                let err = $err;
+                                match on_error {
+                                    OnError::Fail => return Err(err),
+                                    OnError::SkipBlock => {
+                                        errors.lock().unwrap().push(SkippedBlock {
+                                            offset: m.offset,
+                                            error: err.to_string(),
+                                        });
+                                        m.oneshot
+                                            .send(BytesMut::new())
+                                            .map_err(|_e| GzpError::ChannelSend)?;
+                                        continue;
+                                    }
+                                    OnError::Truncate => {
+                                        errors.lock().unwrap().push(SkippedBlock {
+                                            offset: m.offset,
+                                            error: err.to_string(),
+                                        });
+                                        drop(m.oneshot);
+                                        return Ok(());
+                                    }
+                                }
+                            }};
+                        }
+
                         let check_values = format.get_footer_values(&m.buffer[..]);
                         let result = if check_values.amount != 0 {
-                            format.decode_block(
+                            match format.decode_block(
                                 &mut decompressor,
-                                &m.buffer[..m.buffer.len() - 8],
+                                &m.buffer[..m.buffer.len() - F::FOOTER_SIZE],
                                 check_values.amount as usize,
-                            )?
+                            ) {
+                                Ok(result) => result,
+                                Err(e) => recover_or_fail!(e),
+                            }
                         } else {
                             vec![]
                         };
 
-                        let mut check = F::B::new();
-                        check.update(&result);
+                        // Strict mode (the default) recomputes the CRC32 and re-checks ISIZE
+                        // against the block's trailer; the lenient mode trusts the compressed
+                        // stream and skips straight to handing the decompressed bytes back, for
+                        // maximum throughput.
+                        if verify {
+                            if result.len() as u32 != check_values.amount {
+                                recover_or_fail!(GzpError::InvalidHeader(
+                                    "Decompressed length does not match the block's ISIZE"
+                                ));
+                            }
 
-                        if check.sum() != check_values.sum {
-                            return Err(GzpError::InvalidCheck {
-                                found: check.sum(),
-                                expected: check_values.sum,
-                            });
+                            let mut check = F::B::new();
+                            check.update(&result);
+
+                            if check.sum() != check_values.sum {
+                                recover_or_fail!(GzpError::InvalidCheck {
+                                    found: check.sum(),
+                                    expected: check_values.sum,
+                                });
+                            }
                         }
                         m.oneshot
                             .send(BytesMut::from(&result[..]))
@@ -180,20 +548,74 @@ where
             .collect();
 
         // Reader
+        let mut offset: u64 = 0;
+        macro_rules! truncate_or_fail {
+            ($err:expr) => {{
+                let err: GzpError = $err.into();
+                match on_error {
+                    OnError::Fail => return Err(err),
+                    OnError::SkipBlock | OnError::Truncate => {
+                        // Neither recovery mode can make sense of a block whose own framing is
+                        // unreadable: there's no way to know where the next block starts without
+                        // it, so both degrade to stopping cleanly right here.
+                        errors.lock().unwrap().push(SkippedBlock {
+                            offset,
+                            error: err.to_string(),
+                        });
+                        break;
+                    }
+                }
+            }};
+        }
+
         loop {
-            // Read gzip header
+            // Read the block header, stopping cleanly at EOF and erroring on a short read (a
+            // truncated header, as opposed to no header at all) rather than conflating the two.
             let mut buf = vec![0; F::HEADER_SIZE];
-            if let Ok(()) = reader.read_exact(&mut buf) {
-                format.check_header(&buf)?;
-                let size = format.get_block_size(&buf)?;
-                let mut remainder = vec![0; size - F::HEADER_SIZE];
-                reader.read_exact(&mut remainder)?;
-                let (m, r) = DMessage::new_parts(Bytes::from(remainder));
-
-                tx_reader.send(r).map_err(|_e| GzpError::ChannelSend)?;
-                tx.send(m).map_err(|_e| GzpError::ChannelSend)?;
-            } else {
-                break; // EOF
+            match read_block_header_or_eof(&mut reader, &mut buf) {
+                Ok(false) => break, // clean EOF, not another block
+                Ok(true) => {}
+                Err(e) => truncate_or_fail!(e),
+            }
+            if let Err(e) = format.check_header(&buf) {
+                truncate_or_fail!(e);
+            }
+            let size = match format.get_block_size(&buf) {
+                Ok(size) => size,
+                Err(e) => truncate_or_fail!(e),
+            };
+            let remainder_size = match size.checked_sub(F::HEADER_SIZE).ok_or(
+                GzpError::InvalidHeader("Declared block size smaller than the header"),
+            ) {
+                Ok(remainder_size) => remainder_size,
+                Err(e) => truncate_or_fail!(e),
+            };
+            if remainder_size > max_block_size {
+                truncate_or_fail!(GzpError::BlockSizeExceeded(remainder_size, max_block_size));
+            }
+            let mut remainder = vec![0; remainder_size];
+            if let Err(e) = reader.read_exact(&mut remainder) {
+                truncate_or_fail!(e);
+            }
+            let is_eof_marker = format.is_eof_marker(&buf, &remainder);
+            let block_offset = offset;
+            offset += (F::HEADER_SIZE + remainder_size) as u64;
+            // Some formats (e.g. Mgzip's first block, when `with_header_options` is set) carry
+            // variable-length metadata between the fixed-size header and the actual deflate
+            // payload; strip it here so `decode_block` only ever sees payload followed by footer.
+            let header_extra_len = format.header_extra_len(&buf, &remainder);
+            if header_extra_len > 0 {
+                remainder.drain(..header_extra_len);
+            }
+            let (m, r) = DMessage::new_parts(Bytes::from(remainder), block_offset);
+
+            tx_reader.send(r).map_err(|_e| GzpError::ChannelSend)?;
+            tx.send(m).map_err(|_e| GzpError::ChannelSend)?;
+
+            if is_eof_marker && !multi_member {
+                // Stop right at this member's end, leaving whatever follows (which may belong to
+                // an outer container rather than another member) unread.
+                break;
             }
         }
         drop(tx);
@@ -223,22 +645,49 @@ where
     }
 }
 
+/// Like [`Read::read_exact`], but returns `Ok(false)` instead of erroring on a clean EOF before
+/// any byte of `buf` has been read (and still errors on a short read after that point), so
+/// [`ParDecompress::run`] can tell a truncated block header apart from the normal end of stream.
+fn read_block_header_or_eof<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = r.read(&mut buf[filled..])?;
+        if n == 0 {
+            return if filled == 0 {
+                Ok(false)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated block header",
+                ))
+            };
+        }
+        filled += n;
+    }
+    Ok(true)
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub(crate) struct DMessage {
     buffer: Bytes,
     oneshot: Sender<BytesMut>,
     is_last: bool,
+    /// Byte offset into the compressed input where this block's header began, recorded so a
+    /// worker that hits [`OnError::SkipBlock`] or [`OnError::Truncate`] can report it in a
+    /// [`SkippedBlock`].
+    offset: u64,
 }
 
 impl DMessage {
-    pub(crate) fn new_parts(buffer: Bytes) -> (Self, Receiver<BytesMut>) {
+    pub(crate) fn new_parts(buffer: Bytes, offset: u64) -> (Self, Receiver<BytesMut>) {
         let (tx, rx) = unbounded();
         (
             DMessage {
                 buffer,
                 oneshot: tx,
                 is_last: false,
+                offset,
             },
             rx,
         )
@@ -251,6 +700,7 @@ where
 {
     // Ok(0) means done
     fn read(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
+        self.ensure_started();
         let mut bytes_copied = 0;
         let asked_for_bytes = buf.len();
         loop {
@@ -294,6 +744,11 @@ where
                                 return Err(err);
                             }
                         };
+                        if self.pending_skip > 0 {
+                            let skip = std::cmp::min(self.pending_skip, self.buffer.len());
+                            self.buffer.advance(skip);
+                            self.pending_skip = 0;
+                        }
                     }
                     Err(_recv_error) => {
                         // If an error occurred receiving, that means the senders have been dropped and the
@@ -338,3 +793,1502 @@ where
         }
     }
 }
+
+impl<F> io::Seek for ParDecompress<F>
+where
+    F: BlockFormatSpec,
+{
+    /// Seek to an uncompressed position, using the index loaded by
+    /// [`ParDecompress::load_index`] to jump the underlying reader directly to the block
+    /// containing it.
+    ///
+    /// Only possible before the background reader thread has started, i.e. before the first
+    /// [`Read::read`] or [`io::Seek::seek`] call on a [`ParDecompress`] built via
+    /// [`ParDecompressBuilder::from_seekable_reader`]; once streaming has begun, the reader has
+    /// been moved onto that thread and can no longer be seeked directly, so this returns an
+    /// [`io::ErrorKind::Unsupported`] error.
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pending = match &mut self.pending {
+            Some(pending) => pending,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "cannot seek a ParDecompress once it has started reading",
+                ))
+            }
+        };
+        if self.index.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no index loaded; call ParDecompress::load_index before seeking",
+            ));
+        }
+        let target = match pos {
+            io::SeekFrom::Start(offset) => offset,
+            io::SeekFrom::Current(offset) => offset.max(0) as u64,
+            io::SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "seeking relative to the end of a ParDecompress stream is not supported",
+                ))
+            }
+        };
+
+        let block_idx = match self
+            .index
+            .binary_search_by_key(&target, |entry| entry.uncompressed_offset)
+        {
+            Ok(i) => i,
+            Err(0) => 0,
+            Err(i) => i - 1,
+        };
+        let entry = self.index[block_idx];
+
+        pending
+            .reader
+            .seek(io::SeekFrom::Start(entry.compressed_offset))?;
+        self.pending_skip = (target - entry.uncompressed_offset) as usize;
+        Ok(target)
+    }
+}
+
+//////////////////////////////////////////////////////////
+// PLAIN (NON-BLOCK) GZIP SCANNING
+//////////////////////////////////////////////////////////
+
+/// Plain gzip (what [`flate2::read::MultiGzDecoder`] reads, e.g. [`crate::deflate::Gzip`]'s own
+/// output, or any ordinary `.gz` file) has no block-size field, so [`ParDecompress`] can't find
+/// member boundaries the way it does for a [`BlockFormatSpec`]. [`GzipParDecompress`] finds them
+/// by scanning instead: the background reader thread parses each member's header (validating
+/// CM/FLG and walking past any optional FEXTRA/FNAME/FCOMMENT/FHCRC sections to the deflate
+/// stream), runs that stream through a throwaway inflate pass to discover where it ends, and
+/// reads the 8-byte CRC32/ISIZE footer to confirm the boundary. Once a member's exact compressed
+/// byte range is known, it's handed to a worker thread -- just like an Mgzip block -- which
+/// performs the real decompression and checks the CRC32. This is what lets an ordinary,
+/// concatenated-multi-member `.gz` file (e.g. several `.gz` files joined end to end) decompress
+/// across multiple cores via the same [`GzipParDecompressBuilder::num_threads`]-configurable API
+/// as [`MgzipSyncReader`](crate::mgzip::MgzipSyncReader) and
+/// [`BgzfSyncReader`](crate::bgzf::BgzfSyncReader), with its `Read` impl reassembling output in
+/// submission order the same way [`ParDecompress`] does for block formats.
+#[cfg(feature = "deflate")]
+pub struct GzipParDecompress {
+    handle: Option<std::thread::JoinHandle<Result<(), GzpError>>>,
+    rx_reader: Option<Receiver<Receiver<BytesMut>>>,
+    buffer: BytesMut,
+}
+
+#[cfg(feature = "deflate")]
+#[derive(Debug)]
+pub struct GzipParDecompressBuilder {
+    num_threads: usize,
+    max_block_size: usize,
+}
+
+#[cfg(feature = "deflate")]
+impl GzipParDecompressBuilder {
+    pub fn new() -> Self {
+        Self {
+            num_threads: num_cpus::get(),
+            max_block_size: DEFAULT_MAX_BLOCK_SIZE,
+        }
+    }
+
+    /// Set the number of threads and verify that they are > 0.
+    pub fn num_threads(mut self, num_threads: usize) -> Result<Self, GzpError> {
+        if num_threads == 0 {
+            return Err(GzpError::NumThreads(num_threads));
+        }
+        self.num_threads = num_threads;
+        Ok(self)
+    }
+
+    /// Set the maximum size a single member's deflate stream is allowed to scan to, guarding
+    /// against a truncated or hostile stream that never signals
+    /// [`Status::StreamEnd`](flate2::Status) from growing the scan buffer unbounded. Defaults to
+    /// [`DEFAULT_MAX_BLOCK_SIZE`].
+    pub fn max_block_size(mut self, max_block_size: usize) -> Self {
+        self.max_block_size = max_block_size;
+        self
+    }
+
+    pub fn from_reader<R: Read + Send + 'static>(self, reader: R) -> GzipParDecompress {
+        let (tx_reader, rx_reader) = bounded(self.num_threads * 2);
+        let num_threads = self.num_threads;
+        let max_block_size = self.max_block_size;
+        let handle = std::thread::spawn(move || {
+            GzipParDecompress::run(&tx_reader, reader, num_threads, max_block_size)
+        });
+        GzipParDecompress {
+            handle: Some(handle),
+            rx_reader: Some(rx_reader),
+            buffer: BytesMut::new(),
+        }
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Default for GzipParDecompressBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A message sent from the [`GzipParDecompress`] reader thread to a worker: the raw deflate bytes
+/// of one member (boundaries already found by scanning) plus the CRC32/ISIZE its footer declared,
+/// for the worker to check against the real decompression it performs.
+#[cfg(feature = "deflate")]
+#[derive(Debug)]
+#[allow(dead_code)]
+struct GMessage {
+    compressed: Bytes,
+    expected_crc: u32,
+    expected_size: u32,
+    oneshot: Sender<BytesMut>,
+}
+
+#[cfg(feature = "deflate")]
+impl GMessage {
+    fn new_parts(
+        compressed: Bytes,
+        expected_crc: u32,
+        expected_size: u32,
+    ) -> (Self, Receiver<BytesMut>) {
+        let (tx, rx) = unbounded();
+        (
+            Self {
+                compressed,
+                expected_crc,
+                expected_size,
+                oneshot: tx,
+            },
+            rx,
+        )
+    }
+}
+
+/// RFC 1952 FLG bit indicating an extra field is present.
+#[cfg(feature = "deflate")]
+const GZIP_FEXTRA: u8 = 0x04;
+/// RFC 1952 FLG bit indicating a NUL-terminated original filename follows.
+#[cfg(feature = "deflate")]
+const GZIP_FNAME: u8 = 0x08;
+/// RFC 1952 FLG bit indicating a NUL-terminated comment follows.
+#[cfg(feature = "deflate")]
+const GZIP_FCOMMENT: u8 = 0x10;
+/// RFC 1952 FLG bit indicating a header CRC16 follows the header.
+#[cfg(feature = "deflate")]
+const GZIP_FHCRC: u8 = 0x02;
+
+#[cfg(feature = "deflate")]
+impl GzipParDecompress {
+    pub fn builder() -> GzipParDecompressBuilder {
+        GzipParDecompressBuilder::new()
+    }
+
+    #[allow(clippy::needless_collect)]
+    fn run<R>(
+        tx_reader: &Sender<Receiver<BytesMut>>,
+        reader: R,
+        num_threads: usize,
+        max_block_size: usize,
+    ) -> Result<(), GzpError>
+    where
+        R: Read + Send + 'static,
+    {
+        let mut reader = io::BufReader::with_capacity(BUFSIZE, reader);
+
+        // Scan the first member before spinning up a worker pool. If the stream is already at EOF
+        // right after it, this is a single-member file -- the common case, which includes every
+        // `.gz` that [`crate::deflate::Gzip`] writes, however many threads compressed it -- so
+        // decompress it right here and skip the thread/channel overhead entirely.
+        let (compressed, expected_crc, expected_size) = match scan_member(&mut reader, max_block_size)? {
+            None => return Ok(()), // empty stream, no members at all
+            Some(m) => m,
+        };
+        if reader.fill_buf()?.is_empty() {
+            let output = decompress_member(&compressed, expected_crc, expected_size)?;
+            let (otx, orx) = unbounded();
+            otx.send(output).map_err(|_e| GzpError::ChannelSend)?;
+            tx_reader.send(orx).map_err(|_e| GzpError::ChannelSend)?;
+            return Ok(());
+        }
+
+        let (tx, rx): (Sender<GMessage>, Receiver<GMessage>) = bounded(num_threads * 2);
+
+        let handles: Vec<JoinHandle<Result<(), GzpError>>> = (0..num_threads)
+            .map(|_| {
+                let rx = rx.clone();
+                std::thread::spawn(move || -> Result<(), GzpError> {
+                    while let Ok(m) = rx.recv() {
+                        let output = decompress_member(&m.compressed, m.expected_crc, m.expected_size)?;
+                        m.oneshot
+                            .send(output)
+                            .map_err(|_e| GzpError::ChannelSend)?;
+                    }
+                    Ok(())
+                })
+            })
+            // This collect is needed to force the evaluation, otherwise this thread will block on writes waiting
+            // for data to show up that will never come since the iterator is lazy.
+            .collect();
+
+        // Dispatch the member already scanned above, then keep scanning the rest of the stream the
+        // same way, handing each one's exact compressed byte range to the worker pool.
+        let (m, r) = GMessage::new_parts(Bytes::from(compressed), expected_crc, expected_size);
+        tx_reader.send(r).map_err(|_e| GzpError::ChannelSend)?;
+        tx.send(m).map_err(|_e| GzpError::ChannelSend)?;
+        loop {
+            match scan_member(&mut reader, max_block_size)? {
+                None => break, // clean EOF between members
+                Some((compressed, expected_crc, expected_size)) => {
+                    let (m, r) =
+                        GMessage::new_parts(Bytes::from(compressed), expected_crc, expected_size);
+                    tx_reader.send(r).map_err(|_e| GzpError::ChannelSend)?;
+                    tx.send(m).map_err(|_e| GzpError::ChannelSend)?;
+                }
+            }
+        }
+        drop(tx);
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| match handle.join() {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e),
+            })
+    }
+
+    /// Close things in such a way as to get errors
+    pub fn finish(&mut self) -> Result<(), GzpError> {
+        if self.rx_reader.is_some() {
+            drop(self.rx_reader.take());
+        }
+        if self.handle.is_some() {
+            match self.handle.take().unwrap().join() {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e),
+            }
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Inflate one member's raw deflate bytes (as found by [`scan_member`]) and check the result
+/// against the CRC32/ISIZE its footer declared. Shared by [`GzipParDecompress::run`]'s worker
+/// threads and its single-member serial fast path.
+#[cfg(feature = "deflate")]
+fn decompress_member(
+    compressed: &[u8],
+    expected_crc: u32,
+    expected_size: u32,
+) -> Result<BytesMut, GzpError> {
+    let mut decompressor = Decompress::new(false);
+    let mut output = vec![0; expected_size as usize];
+    decompressor.decompress(compressed, &mut output, FlushDecompress::Finish)?;
+
+    let mut check = Crc32::new();
+    check.update(&output);
+    if check.sum() != expected_crc {
+        return Err(GzpError::InvalidCheck {
+            found: check.sum(),
+            expected: expected_crc,
+        });
+    }
+    Ok(BytesMut::from(&output[..]))
+}
+
+/// Read one gzip member's header, then scan its deflate stream (via a throwaway inflate pass,
+/// since plain gzip has no block-size field) to find where it ends, returning its raw compressed
+/// bytes and the CRC32/ISIZE its footer declares. Returns `None` on a clean EOF before any more
+/// member's header, the normal end of a concatenated gzip stream.
+#[cfg(feature = "deflate")]
+fn scan_member<R: BufRead>(
+    r: &mut R,
+    max_block_size: usize,
+) -> Result<Option<(Vec<u8>, u32, u32)>, GzpError> {
+    let mut fixed = [0u8; 10];
+    if !read_exact_or_eof(r, &mut fixed)? {
+        return Ok(None);
+    }
+    if fixed[0] != 0x1f || fixed[1] != 0x8b {
+        return Err(GzpError::InvalidHeader("Bad gzip magic"));
+    }
+    if fixed[2] != 8 {
+        return Err(GzpError::InvalidHeader("Unsupported compression method"));
+    }
+    let flags = fixed[3];
+    if flags & GZIP_FEXTRA != 0 {
+        let mut xlen_buf = [0u8; 2];
+        r.read_exact(&mut xlen_buf)?;
+        let xlen = LittleEndian::read_u16(&xlen_buf) as usize;
+        let mut extra = vec![0u8; xlen];
+        r.read_exact(&mut extra)?;
+    }
+    if flags & GZIP_FNAME != 0 {
+        skip_nul_terminated(r)?;
+    }
+    if flags & GZIP_FCOMMENT != 0 {
+        skip_nul_terminated(r)?;
+    }
+    if flags & GZIP_FHCRC != 0 {
+        let mut discard = [0u8; 2];
+        r.read_exact(&mut discard)?;
+    }
+
+    let mut decompressor = Decompress::new(false);
+    let mut scratch = vec![0u8; BUFSIZE];
+    let mut compressed = Vec::new();
+    loop {
+        let (status, consumed) = {
+            let avail = r.fill_buf()?;
+            if avail.is_empty() {
+                return Err(GzpError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated deflate stream in gzip member",
+                )));
+            }
+            let before_in = decompressor.total_in();
+            let status = decompressor.decompress(avail, &mut scratch, FlushDecompress::None)?;
+            let consumed = (decompressor.total_in() - before_in) as usize;
+            compressed.extend_from_slice(&avail[..consumed]);
+            (status, consumed)
+        };
+        r.consume(consumed);
+        if compressed.len() > max_block_size {
+            return Err(GzpError::BlockSizeExceeded(compressed.len(), max_block_size));
+        }
+        if status == Status::StreamEnd {
+            break;
+        }
+    }
+
+    let mut footer = [0u8; 8];
+    r.read_exact(&mut footer)?;
+    let expected_crc = LittleEndian::read_u32(&footer[..4]);
+    let expected_size = LittleEndian::read_u32(&footer[4..]);
+    if decompressor.total_out() as u32 != expected_size {
+        return Err(GzpError::InvalidHeader(
+            "ISIZE does not match the decompressed length found while scanning",
+        ));
+    }
+
+    Ok(Some((compressed, expected_crc, expected_size)))
+}
+
+/// Like [`Read::read_exact`], but returns `Ok(false)` instead of erroring on a clean EOF before
+/// any byte of `buf` has been read (and still errors on a short read after that point).
+#[cfg(feature = "deflate")]
+fn read_exact_or_eof<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = r.read(&mut buf[filled..])?;
+        if n == 0 {
+            return if filled == 0 {
+                Ok(false)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated gzip member header",
+                ))
+            };
+        }
+        filled += n;
+    }
+    Ok(true)
+}
+
+/// Skip a NUL-terminated field (FNAME or FCOMMENT).
+#[cfg(feature = "deflate")]
+fn skip_nul_terminated<R: BufRead>(r: &mut R) -> io::Result<()> {
+    let mut byte = [0u8; 1];
+    loop {
+        r.read_exact(&mut byte)?;
+        if byte[0] == 0 {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Read for GzipParDecompress {
+    // Ok(0) means done
+    fn read(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
+        let mut bytes_copied = 0;
+        let asked_for_bytes = buf.len();
+        loop {
+            if bytes_copied == asked_for_bytes {
+                break;
+            }
+
+            if !self.buffer.is_empty() {
+                let curr_len = self.buffer.len();
+                let to_copy = &self
+                    .buffer
+                    .split_to(std::cmp::min(buf.remaining_mut(), curr_len));
+
+                buf.put(&to_copy[..]);
+                bytes_copied += to_copy.len();
+            } else if self.rx_reader.is_some() {
+                match self.rx_reader.as_mut().unwrap().recv() {
+                    Ok(new_buffer_chan) => {
+                        self.buffer = match new_buffer_chan.recv() {
+                            Ok(b) => b,
+                            Err(_recv_error) => {
+                                let error = match self.handle.take().unwrap().join() {
+                                    Ok(result) => result,
+                                    Err(e) => std::panic::resume_unwind(e),
+                                };
+                                let err = match error {
+                                    Ok(()) => {
+                                        self.rx_reader.take();
+                                        break;
+                                    }
+                                    Err(GzpError::Io(ioerr)) => ioerr,
+                                    Err(err) => io::Error::new(io::ErrorKind::Other, err),
+                                };
+                                self.rx_reader.take();
+                                return Err(err);
+                            }
+                        };
+                    }
+                    Err(_recv_error) => {
+                        let error = match self.handle.take().unwrap().join() {
+                            Ok(result) => result,
+                            Err(e) => std::panic::resume_unwind(e),
+                        };
+                        let err = match error {
+                            Ok(()) => {
+                                self.rx_reader.take();
+                                break;
+                            }
+                            Err(GzpError::Io(ioerr)) => ioerr,
+                            Err(err) => io::Error::new(io::ErrorKind::Other, err),
+                        };
+                        self.rx_reader.take();
+                        return Err(err);
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(bytes_copied)
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Drop for GzipParDecompress {
+    fn drop(&mut self) {
+        if self.rx_reader.is_some() {
+            match self.finish() {
+                // ChannelSend errors are acceptable since we just dropped the receiver to cause the shutdown
+                Ok(()) | Err(GzpError::ChannelSend) => (),
+                Err(err) => std::panic::resume_unwind(Box::new(err)),
+            }
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////
+// FORMAT SNIFFING
+//////////////////////////////////////////////////////////
+
+/// How many leading bytes [`AutoDecompressBuilder::from_reader`] peeks to sniff the container
+/// format: the fixed 10-byte gzip prefix, the 2-byte XLEN, and the first 8 bytes of the EXTRA
+/// field, i.e. [`Mgzip::HEADER_SIZE`] in all (the largest of BGZF's/Mgzip's own `HEADER_SIZE`).
+///
+/// [`Mgzip::HEADER_SIZE`]: crate::deflate::Mgzip
+#[cfg(feature = "deflate")]
+const SNIFF_LEN: usize = 20;
+
+/// A [`Read`] implementation that dispatches to the right parallel decompressor after
+/// [`AutoDecompressBuilder::from_reader`] sniffs the container format from the stream's leading
+/// bytes, so callers that only have an arbitrary file don't have to know ahead of time whether
+/// it's Mgzip, BGZF, plain (possibly multi-member) gzip, a zlib stream, a Snappy frame stream, or
+/// (as the catch-all fallback) headerless raw DEFLATE.
+#[cfg(feature = "deflate")]
+pub enum AutoDecompress {
+    Bgzf(ParDecompress<crate::deflate::Bgzf>),
+    Mgzip(ParDecompress<crate::deflate::Mgzip>),
+    Gzip(GzipParDecompress),
+    #[cfg(feature = "snappy")]
+    Snap(ParSnapDecompress),
+    #[cfg(feature = "any_zlib")]
+    Zlib(ParZlibDecompress),
+    #[cfg(feature = "zstd")]
+    Zstd(ParDecompress<crate::zstd::Zstd>),
+    #[cfg(feature = "lz4")]
+    Lz4(ParDecompress<crate::lz4::Lz4>),
+    /// Raw DEFLATE has no magic bytes or framing of its own to detect, so this is the fallback
+    /// once nothing else matches, decoded synchronously (there's no self-describing block
+    /// structure for a worker pool to split on) via a plain [`DeflateDecoder`].
+    RawDeflate(Box<dyn Read + Send>),
+}
+
+/// Which container format [`AutoDecompress`] detected.
+///
+/// A lighter-weight alternative to matching on [`AutoDecompress`] itself, for callers that want
+/// to log or branch on the detected format without naming every decompressor type.
+///
+/// Only covers the formats this crate itself knows how to write (gzip/BGZF/Mgzip/zlib, plus
+/// Snappy frames behind the `snappy` feature, and Zstd/Lz4 behind their own features), plus raw
+/// DEFLATE as the fallback; formats with no writer here, like bzip2 or xz, aren't sniffed or
+/// supported.
+#[cfg(feature = "deflate")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Bgzf,
+    Mgzip,
+    Gzip,
+    #[cfg(feature = "snappy")]
+    Snap,
+    #[cfg(feature = "any_zlib")]
+    Zlib,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "lz4")]
+    Lz4,
+    RawDeflate,
+}
+
+#[cfg(feature = "deflate")]
+impl AutoDecompress {
+    /// The container format detected for this reader.
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            AutoDecompress::Bgzf(_) => Algorithm::Bgzf,
+            AutoDecompress::Mgzip(_) => Algorithm::Mgzip,
+            AutoDecompress::Gzip(_) => Algorithm::Gzip,
+            #[cfg(feature = "snappy")]
+            AutoDecompress::Snap(_) => Algorithm::Snap,
+            #[cfg(feature = "any_zlib")]
+            AutoDecompress::Zlib(_) => Algorithm::Zlib,
+            #[cfg(feature = "zstd")]
+            AutoDecompress::Zstd(_) => Algorithm::Zstd,
+            #[cfg(feature = "lz4")]
+            AutoDecompress::Lz4(_) => Algorithm::Lz4,
+            AutoDecompress::RawDeflate(_) => Algorithm::RawDeflate,
+        }
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Read for AutoDecompress {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            AutoDecompress::Bgzf(r) => r.read(buf),
+            AutoDecompress::Mgzip(r) => r.read(buf),
+            AutoDecompress::Gzip(r) => r.read(buf),
+            #[cfg(feature = "snappy")]
+            AutoDecompress::Snap(r) => r.read(buf),
+            #[cfg(feature = "any_zlib")]
+            AutoDecompress::Zlib(r) => r.read(buf),
+            #[cfg(feature = "zstd")]
+            AutoDecompress::Zstd(r) => r.read(buf),
+            #[cfg(feature = "lz4")]
+            AutoDecompress::Lz4(r) => r.read(buf),
+            AutoDecompress::RawDeflate(r) => r.read(buf),
+        }
+    }
+}
+
+/// Builder for [`AutoDecompress`].
+#[cfg(feature = "deflate")]
+#[derive(Debug)]
+pub struct AutoDecompressBuilder {
+    num_threads: usize,
+    pin_threads: Option<usize>,
+    max_block_size: usize,
+}
+
+#[cfg(feature = "deflate")]
+impl AutoDecompressBuilder {
+    pub fn new() -> Self {
+        Self {
+            num_threads: num_cpus::get(),
+            pin_threads: None,
+            max_block_size: DEFAULT_MAX_BLOCK_SIZE,
+        }
+    }
+
+    /// Set the number of threads and verify that they are > 0.
+    pub fn num_threads(mut self, num_threads: usize) -> Result<Self, GzpError> {
+        if num_threads == 0 {
+            return Err(GzpError::NumThreads(num_threads));
+        }
+        self.num_threads = num_threads;
+        Ok(self)
+    }
+
+    /// Set the [`pin_threads`](ParDecompressBuilder.pin_threads). Only applies to the BGZF and
+    /// Mgzip paths.
+    pub fn pin_threads(mut self, pin_threads: Option<usize>) -> Self {
+        self.pin_threads = pin_threads;
+        self
+    }
+
+    /// Set the maximum declared/scanned block size. See
+    /// [`ParDecompressBuilder::max_block_size`] / [`GzipParDecompressBuilder::max_block_size`].
+    /// Ignored by the Snappy path.
+    pub fn max_block_size(mut self, max_block_size: usize) -> Self {
+        self.max_block_size = max_block_size;
+        self
+    }
+
+    /// Sniff the container format from `reader`'s leading bytes without losing them, then build
+    /// the matching parallel decompressor.
+    ///
+    /// Recognizes, in order:
+    /// - BGZF: gzip magic, FEXTRA set, with a `BC` subfield.
+    /// - Mgzip: gzip magic, FEXTRA set, with an `IG` subfield.
+    /// - Plain (possibly multi-member) gzip: gzip magic, anything else.
+    /// - Snappy frame (only if the `snappy` feature is enabled): a leading `0xff` chunk of length
+    ///   6 containing `sNaPpY`.
+    /// - Zlib (only if the `any_zlib` feature is enabled): a 2-byte RFC 1950 header that passes
+    ///   [`crate::zlib::check_header`]'s FCHECK/CM/CINFO validation.
+    /// - Zstd (only if the `zstd` feature is enabled): gzp's own `ZSTB` block-wrapper magic.
+    /// - Lz4 (only if the `lz4` feature is enabled): gzp's own `LZ4B` block-wrapper magic.
+    /// - Raw DEFLATE: the fallback once nothing else matches, since it has no magic bytes of its
+    ///   own to detect.
+    pub fn from_reader<R: Read + Send + 'static>(
+        self,
+        mut reader: R,
+    ) -> Result<AutoDecompress, GzpError> {
+        let mut sniffed = vec![0u8; SNIFF_LEN];
+        let mut filled = 0;
+        while filled < SNIFF_LEN {
+            let n = reader.read(&mut sniffed[filled..])?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        sniffed.truncate(filled);
+
+        // Prepend the sniffed bytes back onto the stream so nothing the peek consumed is lost.
+        let chained = io::Read::chain(io::Cursor::new(sniffed.clone()), reader);
+
+        if sniffed.len() >= 2 && sniffed[0] == 0x1f && sniffed[1] == 0x8b {
+            return if has_extra_subfield(&sniffed, b'B', b'C') {
+                Ok(AutoDecompress::Bgzf(
+                    ParDecompressBuilder::<crate::deflate::Bgzf>::new()
+                        .num_threads(self.num_threads)?
+                        .pin_threads(self.pin_threads)
+                        .max_block_size(self.max_block_size)
+                        .from_reader(chained),
+                ))
+            } else if has_extra_subfield(&sniffed, b'I', b'G') {
+                Ok(AutoDecompress::Mgzip(
+                    ParDecompressBuilder::<crate::deflate::Mgzip>::new()
+                        .num_threads(self.num_threads)?
+                        .pin_threads(self.pin_threads)
+                        .max_block_size(self.max_block_size)
+                        .from_reader(chained),
+                ))
+            } else {
+                Ok(AutoDecompress::Gzip(
+                    GzipParDecompressBuilder::new()
+                        .num_threads(self.num_threads)?
+                        .max_block_size(self.max_block_size)
+                        .from_reader(chained),
+                ))
+            };
+        }
+
+        #[cfg(feature = "snappy")]
+        if sniffed.len() >= 10
+            && sniffed[0] == 0xff
+            && sniffed[1..4] == [6, 0, 0]
+            && &sniffed[4..10] == b"sNaPpY"
+        {
+            return Ok(AutoDecompress::Snap(
+                ParSnapDecompressBuilder::new()
+                    .num_threads(self.num_threads)?
+                    .from_reader(chained),
+            ));
+        }
+
+        #[cfg(feature = "any_zlib")]
+        if sniffed.len() >= 2 && crate::zlib::check_header([sniffed[0], sniffed[1]]).is_ok() {
+            return Ok(AutoDecompress::Zlib(
+                ParZlibDecompressBuilder::new()
+                    .num_threads(self.num_threads)?
+                    .max_block_size(self.max_block_size)
+                    .from_reader(chained),
+            ));
+        }
+
+        #[cfg(feature = "zstd")]
+        if sniffed.len() >= 4 && sniffed[..4] == crate::zstd::MAGIC {
+            return Ok(AutoDecompress::Zstd(
+                ParDecompressBuilder::<crate::zstd::Zstd>::new()
+                    .num_threads(self.num_threads)?
+                    .max_block_size(self.max_block_size)
+                    .from_reader(chained),
+            ));
+        }
+
+        #[cfg(feature = "lz4")]
+        if sniffed.len() >= 4 && sniffed[..4] == crate::lz4::WRAPPER_MAGIC {
+            return Ok(AutoDecompress::Lz4(
+                ParDecompressBuilder::<crate::lz4::Lz4>::new()
+                    .num_threads(self.num_threads)?
+                    .max_block_size(self.max_block_size)
+                    .from_reader(chained),
+            ));
+        }
+
+        // Nothing recognized: assume headerless raw DEFLATE, the only format left with no magic
+        // bytes of its own to rule it out.
+        Ok(AutoDecompress::RawDeflate(Box::new(DeflateDecoder::new(
+            io::BufReader::new(chained),
+        ))))
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Default for AutoDecompressBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether the sniffed header's EXTRA field (if FLG.FEXTRA is set) contains a subfield whose
+/// SI1/SI2 match `si1`/`si2`.
+#[cfg(feature = "deflate")]
+fn has_extra_subfield(sniffed: &[u8], si1: u8, si2: u8) -> bool {
+    if sniffed.len() < 12 || sniffed[3] & 0x04 == 0 {
+        return false;
+    }
+    let xlen = LittleEndian::read_u16(&sniffed[10..12]) as usize;
+    let extra = &sniffed[12..std::cmp::min(12 + xlen, sniffed.len())];
+    let mut i = 0;
+    while i + 4 <= extra.len() {
+        if extra[i] == si1 && extra[i + 1] == si2 {
+            return true;
+        }
+        let slen = LittleEndian::read_u16(&extra[i + 2..i + 4]) as usize;
+        i += 4 + slen;
+    }
+    false
+}
+
+//////////////////////////////////////////////////////////
+// SNAPPY FRAME SCANNING
+//////////////////////////////////////////////////////////
+
+/// The Snappy frame format (what [`snap::read::FrameDecoder`] reads, and [`crate::snap::Snap`]
+/// writes) is a sequence of length-prefixed chunks, each decodable independently of the others,
+/// so [`ParSnapDecompress`] can dispatch chunks to a worker pool much like [`GzipParDecompress`]
+/// dispatches gzip members -- except a Snappy chunk's length is given directly in its header,
+/// with no need to scan for it.
+#[cfg(feature = "snappy")]
+pub struct ParSnapDecompress {
+    handle: Option<std::thread::JoinHandle<Result<(), GzpError>>>,
+    rx_reader: Option<Receiver<Receiver<BytesMut>>>,
+    buffer: BytesMut,
+}
+
+#[cfg(feature = "snappy")]
+#[derive(Debug)]
+pub struct ParSnapDecompressBuilder {
+    num_threads: usize,
+}
+
+#[cfg(feature = "snappy")]
+impl ParSnapDecompressBuilder {
+    pub fn new() -> Self {
+        Self {
+            num_threads: num_cpus::get(),
+        }
+    }
+
+    /// Set the number of threads and verify that they are > 0.
+    pub fn num_threads(mut self, num_threads: usize) -> Result<Self, GzpError> {
+        if num_threads == 0 {
+            return Err(GzpError::NumThreads(num_threads));
+        }
+        self.num_threads = num_threads;
+        Ok(self)
+    }
+
+    pub fn from_reader<R: Read + Send + 'static>(self, reader: R) -> ParSnapDecompress {
+        let (tx_reader, rx_reader) = bounded(self.num_threads * 2);
+        let num_threads = self.num_threads;
+        let handle =
+            std::thread::spawn(move || ParSnapDecompress::run(&tx_reader, reader, num_threads));
+        ParSnapDecompress {
+            handle: Some(handle),
+            rx_reader: Some(rx_reader),
+            buffer: BytesMut::new(),
+        }
+    }
+}
+
+#[cfg(feature = "snappy")]
+impl Default for ParSnapDecompressBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which of the two data chunk types a [`SMessage`] came from: compressed chunks need a real
+/// Snappy block decode, uncompressed chunks are just checksummed and passed through.
+#[cfg(feature = "snappy")]
+#[derive(Debug, Clone, Copy)]
+enum SnapChunkKind {
+    Compressed,
+    Uncompressed,
+}
+
+/// A message sent from the [`ParSnapDecompress`] reader thread to a worker: one data chunk's
+/// payload (compressed or literal, per `kind`) plus the masked CRC-32C its header declared, for
+/// the worker to check against the real decompression it performs.
+#[cfg(feature = "snappy")]
+#[derive(Debug)]
+#[allow(dead_code)]
+struct SMessage {
+    kind: SnapChunkKind,
+    payload: Bytes,
+    expected_masked_crc: u32,
+    oneshot: Sender<BytesMut>,
+}
+
+#[cfg(feature = "snappy")]
+impl SMessage {
+    fn new_parts(
+        kind: SnapChunkKind,
+        payload: Bytes,
+        expected_masked_crc: u32,
+    ) -> (Self, Receiver<BytesMut>) {
+        let (tx, rx) = unbounded();
+        (
+            Self {
+                kind,
+                payload,
+                expected_masked_crc,
+                oneshot: tx,
+            },
+            rx,
+        )
+    }
+}
+
+/// Snappy frame chunk type: stream identifier.
+#[cfg(feature = "snappy")]
+const SNAP_STREAM_IDENTIFIER: u8 = 0xff;
+/// Snappy frame chunk type: compressed data.
+#[cfg(feature = "snappy")]
+const SNAP_COMPRESSED_DATA: u8 = 0x00;
+/// Snappy frame chunk type: uncompressed data.
+#[cfg(feature = "snappy")]
+const SNAP_UNCOMPRESSED_DATA: u8 = 0x01;
+
+/// A data chunk decodes to at most this many bytes, per the framing format spec; used as a
+/// sanity bound on a chunk's declared length before allocating its payload buffer.
+#[cfg(feature = "snappy")]
+const MAX_SNAP_CHUNK_PAYLOAD: usize = 65536 + 32;
+
+/// Mask a raw CRC-32C the way the Snappy frame format requires before storing or comparing it:
+/// `((crc >> 15) | (crc << 17)) + 0xa282ead8`. Chunks are compared masked-to-masked, so there's
+/// no need to ever unmask a value read from the stream.
+#[cfg(feature = "snappy")]
+#[inline]
+fn mask_crc32c(crc: u32) -> u32 {
+    ((crc >> 15) | (crc << 17)).wrapping_add(0xa282ead8)
+}
+
+/// Read a 3-byte little-endian length, as used by Snappy frame chunk headers.
+#[cfg(feature = "snappy")]
+fn read_u24_le(bytes: &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16
+}
+
+/// Like [`Read::read_exact`], but returns `Ok(false)` instead of erroring on a clean EOF before
+/// any byte of `buf` has been read (and still errors on a short read after that point).
+#[cfg(feature = "snappy")]
+fn read_chunk_header_or_eof<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = r.read(&mut buf[filled..])?;
+        if n == 0 {
+            return if filled == 0 {
+                Ok(false)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated Snappy frame chunk header",
+                ))
+            };
+        }
+        filled += n;
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "snappy")]
+impl ParSnapDecompress {
+    pub fn builder() -> ParSnapDecompressBuilder {
+        ParSnapDecompressBuilder::new()
+    }
+
+    #[allow(clippy::needless_collect)]
+    fn run<R>(
+        tx_reader: &Sender<Receiver<BytesMut>>,
+        mut reader: R,
+        num_threads: usize,
+    ) -> Result<(), GzpError>
+    where
+        R: Read + Send + 'static,
+    {
+        let (tx, rx): (Sender<SMessage>, Receiver<SMessage>) = bounded(num_threads * 2);
+
+        let handles: Vec<JoinHandle<Result<(), GzpError>>> = (0..num_threads)
+            .map(|_| {
+                let rx = rx.clone();
+                std::thread::spawn(move || -> Result<(), GzpError> {
+                    let mut decoder = SnapDecoder::new();
+                    while let Ok(m) = rx.recv() {
+                        let output = match m.kind {
+                            SnapChunkKind::Compressed => decoder.decompress_vec(&m.payload)?,
+                            SnapChunkKind::Uncompressed => m.payload.to_vec(),
+                        };
+
+                        let mut check = Crc32c::new();
+                        check.update(&output);
+                        let found = mask_crc32c(check.sum());
+                        if found != m.expected_masked_crc {
+                            return Err(GzpError::InvalidCheck {
+                                found,
+                                expected: m.expected_masked_crc,
+                            });
+                        }
+                        m.oneshot
+                            .send(BytesMut::from(&output[..]))
+                            .map_err(|_e| GzpError::ChannelSend)?;
+                    }
+                    Ok(())
+                })
+            })
+            // This collect is needed to force the evaluation, otherwise this thread will block on writes waiting
+            // for data to show up that will never come since the iterator is lazy.
+            .collect();
+
+        // Reader: parse each chunk's header directly (the length is given up front, unlike
+        // gzip), and dispatch data chunks to the worker pool.
+        loop {
+            let mut header = [0u8; 4];
+            if !read_chunk_header_or_eof(&mut reader, &mut header)? {
+                break; // clean EOF between chunks
+            }
+            let chunk_type = header[0];
+            let length = read_u24_le(&header[1..4]) as usize;
+            match chunk_type {
+                SNAP_STREAM_IDENTIFIER => {
+                    if length != 6 {
+                        return Err(GzpError::InvalidHeader(
+                            "Snappy stream identifier chunk has the wrong length",
+                        ));
+                    }
+                    let mut payload = [0u8; 6];
+                    reader.read_exact(&mut payload)?;
+                    if &payload != b"sNaPpY" {
+                        return Err(GzpError::InvalidHeader("Bad Snappy stream identifier"));
+                    }
+                }
+                SNAP_COMPRESSED_DATA | SNAP_UNCOMPRESSED_DATA => {
+                    if length < 4 {
+                        return Err(GzpError::InvalidHeader(
+                            "Snappy data chunk shorter than its checksum",
+                        ));
+                    }
+                    if length - 4 > MAX_SNAP_CHUNK_PAYLOAD {
+                        return Err(GzpError::BlockSizeExceeded(
+                            length - 4,
+                            MAX_SNAP_CHUNK_PAYLOAD,
+                        ));
+                    }
+                    let mut crc_buf = [0u8; 4];
+                    reader.read_exact(&mut crc_buf)?;
+                    let expected_masked_crc = LittleEndian::read_u32(&crc_buf);
+                    let mut payload = vec![0u8; length - 4];
+                    reader.read_exact(&mut payload)?;
+
+                    let kind = if chunk_type == SNAP_COMPRESSED_DATA {
+                        SnapChunkKind::Compressed
+                    } else {
+                        SnapChunkKind::Uncompressed
+                    };
+                    let (m, r) =
+                        SMessage::new_parts(kind, Bytes::from(payload), expected_masked_crc);
+                    tx_reader.send(r).map_err(|_e| GzpError::ChannelSend)?;
+                    tx.send(m).map_err(|_e| GzpError::ChannelSend)?;
+                }
+                // Reserved skippable chunks (0x80-0xfd) and padding (0xfe): discard their payload.
+                0x80..=0xfe => {
+                    io::copy(&mut (&mut reader).take(length as u64), &mut io::sink())?;
+                }
+                _ => {
+                    return Err(GzpError::InvalidHeader(
+                        "Unsupported (reserved, unskippable) Snappy frame chunk type",
+                    ));
+                }
+            }
+        }
+        drop(tx);
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| match handle.join() {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e),
+            })
+    }
+
+    /// Close things in such a way as to get errors
+    pub fn finish(&mut self) -> Result<(), GzpError> {
+        if self.rx_reader.is_some() {
+            drop(self.rx_reader.take());
+        }
+        if self.handle.is_some() {
+            match self.handle.take().unwrap().join() {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e),
+            }
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "snappy")]
+impl Read for ParSnapDecompress {
+    // Ok(0) means done
+    fn read(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
+        let mut bytes_copied = 0;
+        let asked_for_bytes = buf.len();
+        loop {
+            if bytes_copied == asked_for_bytes {
+                break;
+            }
+
+            if !self.buffer.is_empty() {
+                let curr_len = self.buffer.len();
+                let to_copy = &self
+                    .buffer
+                    .split_to(std::cmp::min(buf.remaining_mut(), curr_len));
+
+                buf.put(&to_copy[..]);
+                bytes_copied += to_copy.len();
+            } else if self.rx_reader.is_some() {
+                match self.rx_reader.as_mut().unwrap().recv() {
+                    Ok(new_buffer_chan) => {
+                        self.buffer = match new_buffer_chan.recv() {
+                            Ok(b) => b,
+                            Err(_recv_error) => {
+                                let error = match self.handle.take().unwrap().join() {
+                                    Ok(result) => result,
+                                    Err(e) => std::panic::resume_unwind(e),
+                                };
+                                let err = match error {
+                                    Ok(()) => {
+                                        self.rx_reader.take();
+                                        break;
+                                    }
+                                    Err(GzpError::Io(ioerr)) => ioerr,
+                                    Err(err) => io::Error::new(io::ErrorKind::Other, err),
+                                };
+                                self.rx_reader.take();
+                                return Err(err);
+                            }
+                        };
+                    }
+                    Err(_recv_error) => {
+                        let error = match self.handle.take().unwrap().join() {
+                            Ok(result) => result,
+                            Err(e) => std::panic::resume_unwind(e),
+                        };
+                        let err = match error {
+                            Ok(()) => {
+                                self.rx_reader.take();
+                                break;
+                            }
+                            Err(GzpError::Io(ioerr)) => ioerr,
+                            Err(err) => io::Error::new(io::ErrorKind::Other, err),
+                        };
+                        self.rx_reader.take();
+                        return Err(err);
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(bytes_copied)
+    }
+}
+
+#[cfg(feature = "snappy")]
+impl Drop for ParSnapDecompress {
+    fn drop(&mut self) {
+        if self.rx_reader.is_some() {
+            match self.finish() {
+                // ChannelSend errors are acceptable since we just dropped the receiver to cause the shutdown
+                Ok(()) | Err(GzpError::ChannelSend) => (),
+                Err(err) => std::panic::resume_unwind(Box::new(err)),
+            }
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////
+// ZLIB SCANNING
+//////////////////////////////////////////////////////////
+
+/// Unlike BGZF/Mgzip, RFC 1950's 2-byte zlib header has no room for a block-size field, so
+/// [`crate::deflate::Zlib`] can't implement [`BlockFormatSpec`] the way [`crate::deflate::Bgzf`]
+/// and [`crate::deflate::Mgzip`] do. [`ParZlibDecompress`] instead finds stream boundaries by
+/// scanning, the same way [`GzipParDecompress`] does for plain gzip: validate the 2-byte header,
+/// run the deflate stream through a throwaway inflate pass to find where it ends, then read the
+/// trailing 4-byte big-endian Adler-32 and hand the exact compressed byte range to a worker
+/// thread for the real decompression and check.
+#[cfg(feature = "any_zlib")]
+pub struct ParZlibDecompress {
+    handle: Option<std::thread::JoinHandle<Result<(), GzpError>>>,
+    rx_reader: Option<Receiver<Receiver<BytesMut>>>,
+    buffer: BytesMut,
+}
+
+#[cfg(feature = "any_zlib")]
+#[derive(Debug)]
+pub struct ParZlibDecompressBuilder {
+    num_threads: usize,
+    max_block_size: usize,
+}
+
+#[cfg(feature = "any_zlib")]
+impl ParZlibDecompressBuilder {
+    pub fn new() -> Self {
+        Self {
+            num_threads: num_cpus::get(),
+            max_block_size: DEFAULT_MAX_BLOCK_SIZE,
+        }
+    }
+
+    /// Set the number of threads and verify that they are > 0.
+    pub fn num_threads(mut self, num_threads: usize) -> Result<Self, GzpError> {
+        if num_threads == 0 {
+            return Err(GzpError::NumThreads(num_threads));
+        }
+        self.num_threads = num_threads;
+        Ok(self)
+    }
+
+    /// Set the maximum size a single stream's deflate body is allowed to scan to, guarding
+    /// against a truncated or hostile stream that never signals
+    /// [`Status::StreamEnd`](flate2::Status) from growing the scan buffer unbounded. Defaults to
+    /// [`DEFAULT_MAX_BLOCK_SIZE`].
+    pub fn max_block_size(mut self, max_block_size: usize) -> Self {
+        self.max_block_size = max_block_size;
+        self
+    }
+
+    pub fn from_reader<R: Read + Send + 'static>(self, reader: R) -> ParZlibDecompress {
+        let (tx_reader, rx_reader) = bounded(self.num_threads * 2);
+        let num_threads = self.num_threads;
+        let max_block_size = self.max_block_size;
+        let handle = std::thread::spawn(move || {
+            ParZlibDecompress::run(&tx_reader, reader, num_threads, max_block_size)
+        });
+        ParZlibDecompress {
+            handle: Some(handle),
+            rx_reader: Some(rx_reader),
+            buffer: BytesMut::new(),
+        }
+    }
+}
+
+#[cfg(feature = "any_zlib")]
+impl Default for ParZlibDecompressBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A message sent from the [`ParZlibDecompress`] reader thread to a worker: one stream's raw
+/// deflate bytes (boundaries already found by scanning) plus the Adler-32 its trailer declared,
+/// for the worker to check against the real decompression it performs.
+#[cfg(feature = "any_zlib")]
+#[derive(Debug)]
+#[allow(dead_code)]
+struct ZMessage {
+    compressed: Bytes,
+    expected_adler32: u32,
+    oneshot: Sender<BytesMut>,
+}
+
+#[cfg(feature = "any_zlib")]
+impl ZMessage {
+    fn new_parts(compressed: Bytes, expected_adler32: u32) -> (Self, Receiver<BytesMut>) {
+        let (tx, rx) = unbounded();
+        (
+            Self {
+                compressed,
+                expected_adler32,
+                oneshot: tx,
+            },
+            rx,
+        )
+    }
+}
+
+#[cfg(feature = "any_zlib")]
+impl ParZlibDecompress {
+    pub fn builder() -> ParZlibDecompressBuilder {
+        ParZlibDecompressBuilder::new()
+    }
+
+    #[allow(clippy::needless_collect)]
+    fn run<R>(
+        tx_reader: &Sender<Receiver<BytesMut>>,
+        reader: R,
+        num_threads: usize,
+        max_block_size: usize,
+    ) -> Result<(), GzpError>
+    where
+        R: Read + Send + 'static,
+    {
+        let (tx, rx): (Sender<ZMessage>, Receiver<ZMessage>) = bounded(num_threads * 2);
+
+        let handles: Vec<JoinHandle<Result<(), GzpError>>> = (0..num_threads)
+            .map(|_| {
+                let rx = rx.clone();
+                std::thread::spawn(move || -> Result<(), GzpError> {
+                    while let Ok(m) = rx.recv() {
+                        let mut decompressor = Decompress::new(false);
+                        let mut output = Vec::with_capacity(4 * m.compressed.len() + 64);
+                        // `decompress_vec` only writes into spare capacity, so grow the buffer
+                        // for as long as the inflater keeps filling it.
+                        loop {
+                            let consumed = decompressor.total_in() as usize;
+                            let status = decompressor.decompress_vec(
+                                &m.compressed[consumed..],
+                                &mut output,
+                                FlushDecompress::Finish,
+                            )?;
+                            if status == Status::StreamEnd || output.len() < output.capacity() {
+                                break;
+                            }
+                            output.reserve(output.capacity());
+                        }
+
+                        let mut check = Adler32::new();
+                        check.update(&output);
+                        if check.sum() != m.expected_adler32 {
+                            return Err(GzpError::InvalidCheck {
+                                found: check.sum(),
+                                expected: m.expected_adler32,
+                            });
+                        }
+                        m.oneshot
+                            .send(BytesMut::from(&output[..]))
+                            .map_err(|_e| GzpError::ChannelSend)?;
+                    }
+                    Ok(())
+                })
+            })
+            // This collect is needed to force the evaluation, otherwise this thread will block on writes waiting
+            // for data to show up that will never come since the iterator is lazy.
+            .collect();
+
+        // Reader: scan each zlib stream by header-walking and a throwaway inflate pass, then
+        // dispatch its exact compressed byte range to the worker pool to actually decompress.
+        let mut reader = io::BufReader::with_capacity(BUFSIZE, reader);
+        loop {
+            match scan_zlib_stream(&mut reader, max_block_size)? {
+                None => break, // clean EOF between streams
+                Some((compressed, expected_adler32)) => {
+                    let (m, r) = ZMessage::new_parts(Bytes::from(compressed), expected_adler32);
+                    tx_reader.send(r).map_err(|_e| GzpError::ChannelSend)?;
+                    tx.send(m).map_err(|_e| GzpError::ChannelSend)?;
+                }
+            }
+        }
+        drop(tx);
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| match handle.join() {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e),
+            })
+    }
+
+    /// Close things in such a way as to get errors
+    pub fn finish(&mut self) -> Result<(), GzpError> {
+        if self.rx_reader.is_some() {
+            drop(self.rx_reader.take());
+        }
+        if self.handle.is_some() {
+            match self.handle.take().unwrap().join() {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e),
+            }
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Read one zlib stream's 2-byte header (validating FCHECK, CM, CINFO, and FDICT), then scan its
+/// deflate body (via a throwaway inflate pass, since RFC 1950 has no block-size field) to find
+/// where it ends, returning its raw compressed bytes and the Adler-32 its trailer declares.
+/// Returns `None` on a clean EOF before any more stream's header, the normal end of a
+/// concatenated zlib stream.
+#[cfg(feature = "any_zlib")]
+fn scan_zlib_stream<R: BufRead>(
+    r: &mut R,
+    max_block_size: usize,
+) -> Result<Option<(Vec<u8>, u32)>, GzpError> {
+    let mut header = [0u8; 2];
+    if !read_exact_or_eof_zlib(r, &mut header)? {
+        return Ok(None);
+    }
+    crate::zlib::check_header(header)?;
+
+    let mut decompressor = Decompress::new(false);
+    let mut scratch = vec![0u8; BUFSIZE];
+    let mut compressed = Vec::new();
+    loop {
+        let (status, consumed) = {
+            let avail = r.fill_buf()?;
+            if avail.is_empty() {
+                return Err(GzpError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated deflate stream in zlib member",
+                )));
+            }
+            let before_in = decompressor.total_in();
+            let status = decompressor.decompress(avail, &mut scratch, FlushDecompress::None)?;
+            let consumed = (decompressor.total_in() - before_in) as usize;
+            compressed.extend_from_slice(&avail[..consumed]);
+            (status, consumed)
+        };
+        r.consume(consumed);
+        if compressed.len() > max_block_size {
+            return Err(GzpError::BlockSizeExceeded(compressed.len(), max_block_size));
+        }
+        if status == Status::StreamEnd {
+            break;
+        }
+    }
+
+    let mut trailer = [0u8; 4];
+    r.read_exact(&mut trailer)?;
+    let expected_adler32 = byteorder::BigEndian::read_u32(&trailer);
+
+    Ok(Some((compressed, expected_adler32)))
+}
+
+/// Like [`Read::read_exact`], but returns `Ok(false)` instead of erroring on a clean EOF before
+/// any byte of `buf` has been read (and still errors on a short read after that point).
+#[cfg(feature = "any_zlib")]
+fn read_exact_or_eof_zlib<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = r.read(&mut buf[filled..])?;
+        if n == 0 {
+            return if filled == 0 {
+                Ok(false)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated zlib header",
+                ))
+            };
+        }
+        filled += n;
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "any_zlib")]
+impl Read for ParZlibDecompress {
+    // Ok(0) means done
+    fn read(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
+        let mut bytes_copied = 0;
+        let asked_for_bytes = buf.len();
+        loop {
+            if bytes_copied == asked_for_bytes {
+                break;
+            }
+
+            if !self.buffer.is_empty() {
+                let curr_len = self.buffer.len();
+                let to_copy = &self
+                    .buffer
+                    .split_to(std::cmp::min(buf.remaining_mut(), curr_len));
+
+                buf.put(&to_copy[..]);
+                bytes_copied += to_copy.len();
+            } else if self.rx_reader.is_some() {
+                match self.rx_reader.as_mut().unwrap().recv() {
+                    Ok(new_buffer_chan) => {
+                        self.buffer = match new_buffer_chan.recv() {
+                            Ok(b) => b,
+                            Err(_recv_error) => {
+                                let error = match self.handle.take().unwrap().join() {
+                                    Ok(result) => result,
+                                    Err(e) => std::panic::resume_unwind(e),
+                                };
+                                let err = match error {
+                                    Ok(()) => {
+                                        self.rx_reader.take();
+                                        break;
+                                    }
+                                    Err(GzpError::Io(ioerr)) => ioerr,
+                                    Err(err) => io::Error::new(io::ErrorKind::Other, err),
+                                };
+                                self.rx_reader.take();
+                                return Err(err);
+                            }
+                        };
+                    }
+                    Err(_recv_error) => {
+                        let error = match self.handle.take().unwrap().join() {
+                            Ok(result) => result,
+                            Err(e) => std::panic::resume_unwind(e),
+                        };
+                        let err = match error {
+                            Ok(()) => {
+                                self.rx_reader.take();
+                                break;
+                            }
+                            Err(GzpError::Io(ioerr)) => ioerr,
+                            Err(err) => io::Error::new(io::ErrorKind::Other, err),
+                        };
+                        self.rx_reader.take();
+                        return Err(err);
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(bytes_copied)
+    }
+}
+
+#[cfg(feature = "any_zlib")]
+impl Drop for ParZlibDecompress {
+    fn drop(&mut self) {
+        if self.rx_reader.is_some() {
+            match self.finish() {
+                // ChannelSend errors are acceptable since we just dropped the receiver to cause the shutdown
+                Ok(()) | Err(GzpError::ChannelSend) => (),
+                Err(err) => std::panic::resume_unwind(Box::new(err)),
+            }
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! Building blocks for parallel compression and decompression.
+//!
+//! - [`compress`] and [`decompress`] each spin up a dedicated set of threads per
+//!   [`ParCompress`](compress::ParCompress) / [`ParDecompress`](decompress::ParDecompress)
+//!   instance.
+//! - [`pool`] shares one fixed-size set of compressor threads across many concurrently open
+//!   output files, for callers that otherwise would need one [`ParCompress`](compress::ParCompress)
+//!   per file.
+
+pub mod compress;
+pub mod decompress;
+pub mod pool;
@@ -0,0 +1,544 @@
+//! A shared compressor thread pool for writing to many outputs at once.
+//!
+//! [`ParCompress`](crate::par::compress::ParCompress) spins up its own `num_threads` compressor
+//! threads (plus a writer thread) for every instance, which is wasteful when an application needs
+//! to write many compressed outputs concurrently (e.g. demultiplexing reads to per-barcode gzip
+//! files) — thousands of output files would mean thousands of compressor threads contending for
+//! the CPU. [`Pool`] instead owns a single fixed set of `N` compressor threads that can be
+//! [exchanged](Pool::exchange) for as many [`PooledWriter`] handles as needed; each handle tracks
+//! its own header/footer/checksum state and the order its chunks were submitted in, but all of
+//! them feed the same shared compressor threads.
+//!
+//! `Pool<F>` is generic over any [`FormatSpec`], so the same subsystem covers the snappy-frame
+//! case (`Pool<`[`Snap`](crate::snap::Snap)`>`) that used to mean a whole tokio runtime per
+//! output, not just the deflate formats shown below.
+//!
+//! This is the N-threads-to-M-writers design: a shared compression queue that the fixed worker
+//! pool pulls from (work-stealing across every exchanged writer), and a per-[`PooledWriter`]
+//! ordering queue of oneshot receivers that each writer drains in submission order to keep its
+//! own byte stream intact. Compression itself goes through the same [`FormatSpec::encode`] every
+//! other writer in this crate uses; [`PooledWriter::finish`] flushes a handle's own buffered tail
+//! and propagates any [`GzpError`] a compressor thread hit, same as [`ZWriter::finish`] elsewhere.
+//!
+//! Writing the finished blocks back out is pooled the same way, on its own fixed set of
+//! [`PoolBuilder::num_writer_threads`] threads: draining a [`PooledWriter`]'s ready chunks and
+//! `write_all`-ing them to its underlying `W` happens on whichever writer thread picks up the job,
+//! not on the thread that called [`Write::write`] or [`Write::flush`] on it, so neither the
+//! caller's thread nor a compressor thread blocks on (possibly slow) output I/O.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "deflate")] {
+//! use std::io::Write;
+//!
+//! use gzp::{deflate::Gzip, par::pool::{Pool, PoolBuilder}, ZWriter};
+//!
+//! let mut pool: Pool<Gzip> = PoolBuilder::new().num_threads(2).unwrap().build();
+//! let mut a = pool.exchange(vec![]);
+//! let mut b = pool.exchange(vec![]);
+//! a.write_all(b"first output\n").unwrap();
+//! b.write_all(b"second output\n").unwrap();
+//! a.finish().unwrap();
+//! b.finish().unwrap();
+//! # }
+//! ```
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use bytes::{Bytes, BytesMut};
+pub use flate2::Compression;
+use flume::{bounded, Receiver, Sender, TryRecvError};
+
+use crate::check::Check;
+use crate::{CompressResult, FormatSpec, GzpError, Message, ZWriter, DICT_SIZE};
+
+/// One unit of deferred writer-side I/O: drain whatever chunks have finished compressing for a
+/// single [`PooledWriter`] and write them out. Picked up by one of [`Pool`]'s shared writer
+/// threads instead of running on the thread that called [`Write::write`]/[`Write::flush`].
+type WriteJob = Box<dyn FnOnce() + Send>;
+
+/// The [`Pool`] builder.
+#[derive(Debug)]
+pub struct PoolBuilder<F>
+where
+    F: FormatSpec,
+{
+    /// The number of shared compressor threads to spawn. Defaults to all available threads.
+    num_threads: usize,
+    /// The number of shared writer threads to spawn. Defaults to 2: output I/O is rarely
+    /// CPU-bound the way compression is, so this pool doesn't need to scale with core count the
+    /// same way [`PoolBuilder::num_threads`] does.
+    num_writer_threads: usize,
+    /// The compression level used by every [`PooledWriter`] exchanged from this pool.
+    compression_level: Compression,
+    /// The out file format to use.
+    format: F,
+    /// Whether or not to pin threads to specific cpus and what core to start pins at.
+    pin_threads: Option<usize>,
+}
+
+impl<F> PoolBuilder<F>
+where
+    F: FormatSpec,
+{
+    /// Create a new [`PoolBuilder`] object.
+    pub fn new() -> Self {
+        Self {
+            num_threads: num_cpus::get(),
+            num_writer_threads: 2,
+            compression_level: Compression::new(3),
+            format: F::new(),
+            pin_threads: None,
+        }
+    }
+
+    /// Set the [`num_threads`](PoolBuilder.num_threads) compressor threads the pool will share.
+    ///
+    /// # Errors
+    /// - [`GzpError::NumThreads`] error if 0 threads selected.
+    pub fn num_threads(mut self, num_threads: usize) -> Result<Self, GzpError> {
+        if num_threads == 0 {
+            return Err(GzpError::NumThreads(num_threads));
+        }
+        self.num_threads = num_threads;
+        Ok(self)
+    }
+
+    /// Set the [`num_writer_threads`](PoolBuilder.num_writer_threads) the pool will share across
+    /// every exchanged [`PooledWriter`].
+    ///
+    /// # Errors
+    /// - [`GzpError::NumThreads`] error if 0 threads selected.
+    pub fn num_writer_threads(mut self, num_writer_threads: usize) -> Result<Self, GzpError> {
+        if num_writer_threads == 0 {
+            return Err(GzpError::NumThreads(num_writer_threads));
+        }
+        self.num_writer_threads = num_writer_threads;
+        Ok(self)
+    }
+
+    /// Set the [`compression_level`](PoolBuilder.compression_level).
+    pub fn compression_level(mut self, compression_level: Compression) -> Self {
+        self.compression_level = compression_level;
+        self
+    }
+
+    /// Set the [`pin_threads`](PoolBuilder.pin_threads).
+    pub fn pin_threads(mut self, pin_threads: Option<usize>) -> Self {
+        self.pin_threads = pin_threads;
+        self
+    }
+
+    /// Spawn the pool's fixed set of compressor and writer threads.
+    pub fn build(self) -> Pool<F> {
+        let (tx_compressor, rx_compressor): (Sender<Message<F::C>>, Receiver<Message<F::C>>) =
+            bounded(self.num_threads * 2);
+        let (tx_writer, rx_writer): (Sender<WriteJob>, Receiver<WriteJob>) =
+            bounded(self.num_writer_threads * 2);
+        let compression_level = self.compression_level;
+        let format = self.format;
+        let pin_threads = self.pin_threads;
+
+        let core_ids = core_affinity::get_core_ids().unwrap();
+        let handles: Vec<JoinHandle<Result<(), GzpError>>> = (0..self.num_threads)
+            .map(|i| {
+                let rx = rx_compressor.clone();
+                let core_ids = core_ids.clone();
+                std::thread::spawn(move || -> Result<(), GzpError> {
+                    if let Some(pin_at) = pin_threads {
+                        if let Some(id) = core_ids.get(pin_at + i) {
+                            core_affinity::set_for_current(*id);
+                        }
+                    }
+
+                    let mut compressor = format.create_compressor(compression_level)?;
+                    while let Ok(m) = rx.recv() {
+                        let chunk = &m.buffer;
+                        let buffer = format.encode(
+                            chunk,
+                            &mut compressor,
+                            compression_level,
+                            m.dictionary.as_ref(),
+                            m.is_first,
+                            m.is_last,
+                        )?;
+                        let mut check = F::create_check();
+                        check.update(chunk);
+
+                        m.oneshot
+                            .send(Ok::<(F::C, Vec<u8>), GzpError>((check, buffer)))
+                            .map_err(|_e| GzpError::ChannelSend)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        let writer_handles: Vec<JoinHandle<()>> = (0..self.num_writer_threads)
+            .map(|_| {
+                let rx_writer = rx_writer.clone();
+                std::thread::spawn(move || {
+                    while let Ok(job) = rx_writer.recv() {
+                        job();
+                    }
+                })
+            })
+            .collect();
+
+        Pool {
+            tx_compressor: Some(tx_compressor),
+            tx_writer: Some(tx_writer),
+            handles,
+            writer_handles,
+            compression_level,
+            format,
+        }
+    }
+}
+
+impl<F> Default for PoolBuilder<F>
+where
+    F: FormatSpec,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fixed-size pool of compressor and writer threads shared by many [`PooledWriter`]s.
+#[allow(unused)]
+pub struct Pool<F>
+where
+    F: FormatSpec,
+{
+    tx_compressor: Option<Sender<Message<F::C>>>,
+    tx_writer: Option<Sender<WriteJob>>,
+    handles: Vec<JoinHandle<Result<(), GzpError>>>,
+    writer_handles: Vec<JoinHandle<()>>,
+    compression_level: Compression,
+    format: F,
+}
+
+impl<F> Pool<F>
+where
+    F: FormatSpec,
+{
+    /// Create a builder to configure the [`Pool`].
+    pub fn builder() -> PoolBuilder<F> {
+        PoolBuilder::new()
+    }
+
+    /// Exchange a `W: Write` for a lightweight [`PooledWriter`] handle that shares this pool's
+    /// compressor and writer threads.
+    pub fn exchange<W: Write + Send + 'static>(&self, writer: W) -> PooledWriter<F, W> {
+        PooledWriter::new(
+            writer,
+            self.tx_compressor.as_ref().unwrap().clone(),
+            self.tx_writer.as_ref().unwrap().clone(),
+            self.compression_level,
+            self.format,
+        )
+    }
+
+    /// Stop accepting new work and join all compressor and writer threads.
+    ///
+    /// Every [`PooledWriter`] exchanged from this pool must be finished (or dropped) first, since
+    /// the compressor threads only exit once every sender, including each writer's clone, has
+    /// been dropped, and the writer threads only exit once every writer's queued jobs have
+    /// finished draining for the same reason.
+    ///
+    /// Calling it again afterwards finds nothing left to stop or join and returns `Ok(())`.
+    pub fn stop_and_join(&mut self) -> Result<(), GzpError> {
+        drop(self.tx_compressor.take());
+        let result = self
+            .handles
+            .drain(..)
+            .try_for_each(|handle| match handle.join() {
+                Ok(result) => result,
+                Err(e) => std::panic::resume_unwind(e),
+            });
+        drop(self.tx_writer.take());
+        for handle in self.writer_handles.drain(..) {
+            if let Err(e) = handle.join() {
+                std::panic::resume_unwind(e);
+            }
+        }
+        result
+    }
+}
+
+impl<F> Drop for Pool<F>
+where
+    F: FormatSpec,
+{
+    fn drop(&mut self) {
+        if self.tx_compressor.is_some() {
+            self.stop_and_join().unwrap();
+        }
+    }
+}
+
+/// A lightweight handle exchanged from a [`Pool`], multiplexing one output `W` over the pool's
+/// shared compressor threads.
+///
+/// Chunks are submitted to the pool's shared compression queue, but each [`PooledWriter`] keeps
+/// its own queue of the oneshot receivers it is waiting on so that, regardless of which
+/// compressor thread finishes a chunk first, bytes are written to `writer` in submission order.
+struct WriterState<F, W>
+where
+    F: FormatSpec,
+    W: Write + Send + 'static,
+{
+    /// This writer's ordering queue: the oneshot receivers for chunks submitted but not yet
+    /// written, in submission order.
+    pending: VecDeque<Receiver<CompressResult<F::C>>>,
+    running_check: F::C,
+    writer: W,
+    /// The first error a writer-thread job hit draining this writer's queue in the background,
+    /// if any. Kept as a string since [`GzpError`] isn't [`Clone`] (the same reason
+    /// [`crate::par::decompress::SkippedBlock`] does). Surfaced to the caller the next time they
+    /// call [`Write::write`], [`Write::flush`], or [`ZWriter::finish`] on this [`PooledWriter`].
+    error: Option<String>,
+}
+
+impl<F, W> WriterState<F, W>
+where
+    F: FormatSpec,
+    W: Write + Send + 'static,
+{
+    /// Write every chunk at the front of the ordering queue that has already finished
+    /// compressing, stopping at the first one that hasn't, to preserve submission order.
+    fn drain_ready(&mut self) -> io::Result<()> {
+        while let Some(rx) = self.pending.front() {
+            match rx.try_recv() {
+                Ok(result) => {
+                    self.pending.pop_front();
+                    let (check, chunk) =
+                        result.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    self.running_check.combine(&check);
+                    self.writer.write_all(&chunk)?;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err(io::Error::new(io::ErrorKind::Other, GzpError::ChannelSend))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Block until every chunk submitted so far has been written, in submission order.
+    fn drain_all(&mut self) -> io::Result<()> {
+        while let Some(rx) = self.pending.pop_front() {
+            let (check, chunk) = rx
+                .recv()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.running_check.combine(&check);
+            self.writer.write_all(&chunk)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct PooledWriter<F, W>
+where
+    F: FormatSpec,
+    W: Write + Send + 'static,
+{
+    tx_compressor: Sender<Message<F::C>>,
+    tx_writer: Sender<WriteJob>,
+    /// Shared with [`Pool`]'s writer threads, which drain ready chunks and write them to
+    /// `state.writer` in the background rather than blocking whichever thread called
+    /// [`Write::write`]/[`Write::flush`] on this handle.
+    state: Arc<Mutex<WriterState<F, W>>>,
+    buffer: BytesMut,
+    dictionary: Option<Bytes>,
+    buffer_size: usize,
+    compression_level: Compression,
+    format: F,
+    wrote_header: bool,
+    finished: bool,
+    /// Whether a `Message` has already been marked `is_first` for this stream.
+    wrote_first_chunk: bool,
+}
+
+impl<F, W> PooledWriter<F, W>
+where
+    F: FormatSpec,
+    W: Write + Send + 'static,
+{
+    fn new(
+        writer: W,
+        tx_compressor: Sender<Message<F::C>>,
+        tx_writer: Sender<WriteJob>,
+        compression_level: Compression,
+        format: F,
+    ) -> Self {
+        Self {
+            tx_compressor,
+            tx_writer,
+            state: Arc::new(Mutex::new(WriterState {
+                pending: VecDeque::new(),
+                running_check: F::create_check(),
+                writer,
+                error: None,
+            })),
+            buffer: BytesMut::with_capacity(F::DEFAULT_BUFSIZE),
+            dictionary: None,
+            buffer_size: F::DEFAULT_BUFSIZE,
+            compression_level,
+            format,
+            wrote_header: false,
+            finished: false,
+            wrote_first_chunk: false,
+        }
+    }
+
+    /// Write the format's header, if it hasn't been written yet.
+    fn ensure_header(&mut self) -> io::Result<()> {
+        if !self.wrote_header {
+            let header = self.format.header(self.compression_level);
+            self.state.lock().unwrap().writer.write_all(&header)?;
+            self.wrote_header = true;
+        }
+        Ok(())
+    }
+
+    /// Return the first error a background writer-thread job recorded, if any, clearing it so it
+    /// isn't reported twice.
+    fn take_background_error(&self) -> io::Result<()> {
+        match self.state.lock().unwrap().error.take() {
+            Some(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Hand a chunk's oneshot receiver off to this writer's ordering queue, and enqueue a job on
+    /// the pool's shared writer threads to drain whatever's ready once it arrives.
+    fn submit(&mut self, rx: Receiver<CompressResult<F::C>>) -> io::Result<()> {
+        self.state.lock().unwrap().pending.push_back(rx);
+        let state = Arc::clone(&self.state);
+        self.tx_writer
+            .send(Box::new(move || {
+                let mut state = state.lock().unwrap();
+                if let Err(e) = state.drain_ready() {
+                    state.error = Some(e.to_string());
+                }
+            }))
+            .map_err(|_e| io::Error::new(io::ErrorKind::Other, GzpError::ChannelSend))?;
+        self.take_background_error()
+    }
+
+    /// Flush this output stream, ensuring all intermediately buffered contents are sent.
+    ///
+    /// If this is the last buffer to be sent, set `is_last` to true to trigger compression
+    /// stream completion.
+    fn flush_last(&mut self, is_last: bool) -> io::Result<()> {
+        self.ensure_header()?;
+        loop {
+            let b = self
+                .buffer
+                .split_to(std::cmp::min(self.buffer.len(), self.buffer_size))
+                .freeze();
+            let (mut m, rx) = Message::new_parts(b, std::mem::replace(&mut self.dictionary, None));
+            if !self.wrote_first_chunk {
+                m.is_first = true;
+                self.wrote_first_chunk = true;
+            }
+            if is_last && self.buffer.is_empty() {
+                m.is_last = true;
+            }
+
+            if m.buffer.len() >= DICT_SIZE && !m.is_last && self.format.needs_dict() {
+                self.dictionary = Some(m.buffer.slice(m.buffer.len() - DICT_SIZE..));
+            }
+
+            self.tx_compressor
+                .send(m)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.submit(rx)?;
+
+            if self.buffer.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F, W> ZWriter for PooledWriter<F, W>
+where
+    F: FormatSpec,
+    W: Write + Send + 'static,
+{
+    /// Flush the buffers, wait on all of this writer's outstanding chunks, and write the footer.
+    ///
+    /// This *MUST* be called before the [`PooledWriter`] goes out of scope. The pool's compressor
+    /// and writer threads keep running afterward so other [`PooledWriter`]s can keep using them.
+    fn finish(&mut self) -> Result<(), GzpError> {
+        if self.finished {
+            return Ok(());
+        }
+        self.flush_last(true)?;
+        let mut state = self.state.lock().unwrap();
+        state.drain_all()?;
+        if let Some(e) = state.error.take() {
+            return Err(io::Error::new(io::ErrorKind::Other, e).into());
+        }
+        let footer = self.format.footer(&state.running_check);
+        state.writer.write_all(&footer)?;
+        state.writer.flush()?;
+        drop(state);
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<F, W> Drop for PooledWriter<F, W>
+where
+    F: FormatSpec,
+    W: Write + Send + 'static,
+{
+    fn drop(&mut self) {
+        self.finish().unwrap();
+    }
+}
+
+impl<F, W> Write for PooledWriter<F, W>
+where
+    F: FormatSpec,
+    W: Write + Send + 'static,
+{
+    /// Write a buffer into this writer, returning how many bytes were written.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while self.buffer.len() > self.buffer_size {
+            self.ensure_header()?;
+            let b = self.buffer.split_to(self.buffer_size).freeze();
+            let (mut m, rx) = Message::new_parts(b, std::mem::replace(&mut self.dictionary, None));
+            if !self.wrote_first_chunk {
+                m.is_first = true;
+                self.wrote_first_chunk = true;
+            }
+            self.dictionary = if self.format.needs_dict() {
+                Some(m.buffer.slice(m.buffer.len() - DICT_SIZE..))
+            } else {
+                None
+            };
+            self.tx_compressor
+                .send(m)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.submit(rx)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Flush this output stream, ensuring all intermediately buffered contents are sent.
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_last(false)
+    }
+}
@@ -1,6 +1,11 @@
 //! Snap compression format.
 //!
-//! This uses the `FrameEncoder` format so each block is a frame.
+//! Each block is framed by hand as a standalone Snappy frame-format chunk (compressed, or
+//! uncompressed when compression doesn't help), reusing a single [`snap::raw::Encoder`] across
+//! blocks instead of rebuilding a [`snap::read::FrameEncoder`] -- and its internal CRC/table setup
+//! -- on every call. [`Snap`] also implements [`crate::BlockFormatSpec`], so
+//! [`crate::par::decompress::ParDecompress`] can decompress it in parallel the same way it does
+//! bgzf, alongside the hand-rolled [`crate::par::decompress::ParSnapDecompress`] worker pool.
 //!
 //! # References
 //!
@@ -21,24 +26,60 @@
 //! parz.finish().unwrap();
 //! # }
 //! ```
-use std::io::{Read, Write};
+use std::io::Write;
 
+use byteorder::{ByteOrder, LittleEndian};
 use bytes::Bytes;
-use snap::read::FrameEncoder;
+use snap::raw::{max_compress_len, Decoder, Encoder};
 
-use crate::check::PassThroughCheck;
+use crate::check::{Check, Crc32c, PassThroughCheck};
 use crate::syncz::SyncZ;
-use crate::{Compression, FormatSpec, GzpError, SyncWriter, ZWriter};
+use crate::{BlockFormatSpec, Compression, FooterValues, FormatSpec, GzpError, SyncWriter, ZWriter};
+
+/// Tag byte identifying a Snappy frame-format compressed-data chunk.
+const CHUNK_COMPRESSED: u8 = 0x00;
+/// Tag byte identifying a Snappy frame-format uncompressed-data chunk.
+const CHUNK_UNCOMPRESSED: u8 = 0x01;
+/// Tag byte identifying the Snappy frame-format stream identifier chunk.
+const CHUNK_STREAM_IDENTIFIER: u8 = 0xff;
+/// The fixed 6-byte payload of the stream identifier chunk.
+const STREAM_IDENTIFIER: &[u8; 6] = b"sNaPpY";
 
 /// Produce snappy deflate stream
 #[derive(Copy, Clone, Debug)]
 pub struct Snap {}
 
-#[allow(unused)]
+/// The CRC-32C mask the Snappy frame format applies to every chunk's checksum: rotate right by 15
+/// bits and add a magic constant, so the stored checksum isn't simply a raw CRC (masking out
+/// accidental zeroes that could otherwise be mistaken for other byte patterns in the stream).
+#[inline]
+fn masked_crc32c(data: &[u8]) -> u32 {
+    let mut check = Crc32c::new();
+    check.update(data);
+    let crc = check.sum();
+    ((crc >> 15) | (crc << 17)).wrapping_add(0xa282_ead8)
+}
+
+/// Invert [`masked_crc32c`]'s rotate-and-add, recovering the raw CRC-32C a chunk header stored.
+#[inline]
+fn unmask_crc32c(masked: u32) -> u32 {
+    let rotated = masked.wrapping_sub(0xa282_ead8);
+    (rotated << 15) | (rotated >> 17)
+}
+
+/// Write a single Snappy frame-format chunk: a 1-byte tag, a 3-byte little-endian chunk length,
+/// then `payload`.
+#[inline]
+fn write_chunk(buffer: &mut Vec<u8>, tag: u8, payload: &[u8]) {
+    buffer.push(tag);
+    let len = payload.len() as u32;
+    buffer.extend_from_slice(&len.to_le_bytes()[..3]);
+    buffer.extend_from_slice(payload);
+}
+
 impl FormatSpec for Snap {
     type C = PassThroughCheck;
-    // TODO: use the raw Encoder and apply same optimizations ad DEFLATE formats
-    type Compressor = ();
+    type Compressor = Encoder;
 
     fn new() -> Self {
         Self {}
@@ -52,9 +93,9 @@ impl FormatSpec for Snap {
     #[inline]
     fn create_compressor(
         &self,
-        compression_level: Compression,
+        _compression_level: Compression,
     ) -> Result<Self::Compressor, GzpError> {
-        Ok(())
+        Ok(Encoder::new())
     }
 
     #[inline]
@@ -62,26 +103,111 @@ impl FormatSpec for Snap {
         &self,
         input: &[u8],
         compressor: &mut Self::Compressor,
-        compression_level: Compression,
-        dict: Option<&Bytes>,
-        is_last: bool,
+        _compression_level: Compression,
+        _dict: Option<&Bytes>,
+        _is_first: bool,
+        _is_last: bool,
     ) -> Result<Vec<u8>, GzpError> {
-        // The plus 8 allows odd small sized blocks to extend up to a byte boundary
-        let mut buffer = Vec::with_capacity(input.len());
-        let mut encoder = FrameEncoder::new(input);
-        encoder.read_to_end(&mut buffer)?;
+        let mut compressed = vec![0u8; max_compress_len(input.len())];
+        let compressed_len = compressor.compress(input, &mut compressed)?;
+        compressed.truncate(compressed_len);
+
+        let crc = masked_crc32c(input);
+        let mut chunk_payload = Vec::with_capacity(4 + compressed.len());
+        chunk_payload.extend_from_slice(&crc.to_le_bytes());
+
+        let mut buffer = Vec::with_capacity(4 + chunk_payload.len() + compressed.len());
+        if compressed.len() < input.len() {
+            chunk_payload.extend_from_slice(&compressed);
+            write_chunk(&mut buffer, CHUNK_COMPRESSED, &chunk_payload);
+        } else {
+            chunk_payload.extend_from_slice(input);
+            write_chunk(&mut buffer, CHUNK_UNCOMPRESSED, &chunk_payload);
+        }
         Ok(buffer)
     }
 
-    fn header(&self, compression_leval: Compression) -> Vec<u8> {
-        vec![]
+    fn header(&self, _compression_level: Compression) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(4 + STREAM_IDENTIFIER.len());
+        write_chunk(&mut buffer, CHUNK_STREAM_IDENTIFIER, STREAM_IDENTIFIER);
+        buffer
     }
 
-    fn footer(&self, check: &Self::C) -> Vec<u8> {
+    fn footer(&self, _check: &Self::C) -> Vec<u8> {
         vec![]
     }
 }
 
+/// [`ParDecompress`](crate::par::decompress::ParDecompress)'s reader loop reads exactly
+/// [`BlockFormatSpec::HEADER_SIZE`] bytes as a block's header and forwards everything else
+/// (`remainder`) to a worker, discarding the header -- so a chunk's tag byte, which says whether
+/// its payload is compressed or stored verbatim, never reaches [`Snap::decode_block`] or
+/// [`Snap::get_footer_values`]. Rather than carry the tag some other way, both methods fall back
+/// to deriving it structurally: [`snap::raw::decompress_len`] only succeeds on a payload that
+/// starts with a valid raw-Snappy-block length prefix, which a stored chunk's verbatim original
+/// bytes essentially never happen to look like, so whether it succeeds reliably tells compressed
+/// and stored chunks apart without needing the tag at all.
+impl BlockFormatSpec for Snap {
+    type B = Crc32c;
+    type Decompressor = Decoder;
+
+    const HEADER_SIZE: usize = 4;
+    // The masked CRC-32C lives at the front of `remainder`, not in a trailing footer.
+    const FOOTER_SIZE: usize = 0;
+
+    fn create_decompressor(&self) -> Self::Decompressor {
+        Decoder::new()
+    }
+
+    #[inline]
+    fn decode_block(
+        &self,
+        decoder: &mut Self::Decompressor,
+        input: &[u8],
+        orig_size: usize,
+    ) -> Result<Vec<u8>, GzpError> {
+        // The first 4 bytes are the masked CRC-32C, already pulled out by get_footer_values.
+        let payload = &input[4..];
+        if payload.len() == orig_size {
+            // This chunk's data didn't shrink when it was written, so it was stored verbatim.
+            Ok(payload.to_vec())
+        } else {
+            let mut result = vec![0u8; orig_size];
+            decoder.decompress(payload, &mut result)?;
+            Ok(result)
+        }
+    }
+
+    #[inline]
+    fn check_header(&self, bytes: &[u8]) -> Result<(), GzpError> {
+        match bytes[0] {
+            CHUNK_COMPRESSED | CHUNK_UNCOMPRESSED | CHUNK_STREAM_IDENTIFIER => Ok(()),
+            _ => Err(GzpError::InvalidHeader("Unsupported Snappy frame chunk type")),
+        }
+    }
+
+    #[inline]
+    fn get_block_size(&self, bytes: &[u8]) -> Result<usize, GzpError> {
+        let length = LittleEndian::read_uint(&bytes[1..4], 3) as usize;
+        Ok(Self::HEADER_SIZE + length)
+    }
+
+    #[inline]
+    fn get_footer_values(&self, input: &[u8]) -> FooterValues {
+        if input == &STREAM_IDENTIFIER[..] {
+            // Nothing to decode or check for the stream identifier chunk.
+            return FooterValues { sum: 0, amount: 0 };
+        }
+        let sum = unmask_crc32c(LittleEndian::read_u32(&input[..4]));
+        let payload = &input[4..];
+        let amount = match snap::raw::decompress_len(payload) {
+            Ok(len) => len as u32,
+            Err(_) => payload.len() as u32,
+        };
+        FooterValues { sum, amount }
+    }
+}
+
 impl<W> SyncWriter<W> for Snap
 where
     W: Write,
@@ -115,6 +241,7 @@ mod test {
     use tempfile::tempdir;
 
     use crate::par::compress::{ParCompress, ParCompressBuilder};
+    use crate::par::decompress::ParSnapDecompressBuilder;
     use crate::syncz::SyncZBuilder;
     use crate::{ZBuilder, ZWriter, BUFSIZE, DICT_SIZE};
 
@@ -153,6 +280,46 @@ mod test {
         assert_eq!(input.to_vec(), bytes);
     }
 
+    #[test]
+    fn test_par_snap_decompress() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes, long enough to span several frame chunks.
+        let input: Vec<u8> = (0..100_000).map(|i| (i % 100) as u8).collect();
+
+        // Compress input to output
+        let mut par_gz: ParCompress<Snap> = ParCompressBuilder::new()
+            .num_threads(4)
+            .unwrap()
+            .from_writer(out_writer);
+        for chunk in input.chunks(1_000) {
+            par_gz.write_all(chunk).unwrap();
+        }
+        par_gz.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // A plain `FrameDecoder` should still be able to read it...
+        let mut gz = FrameDecoder::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+
+        // ...and so should gzp's parallel frame decompressor.
+        let reader = BufReader::new(File::open(&output_file).unwrap());
+        let mut par_d = ParSnapDecompressBuilder::new().from_reader(reader);
+        let mut bytes = vec![];
+        par_d.read_to_end(&mut bytes).unwrap();
+        assert_eq!(input, bytes);
+    }
+
     proptest! {
         #[test]
         #[ignore]
@@ -15,7 +15,7 @@ where
     F: FormatSpec + SyncWriter<W>,
     W: Write,
 {
-    compression_level: Compression,
+    pub(crate) compression_level: Compression,
     format: PhantomData<F>,
     phantom: PhantomData<W>,
 }
@@ -0,0 +1,368 @@
+//! Zlib (RFC 1950) sync reader/writer with explicit header and trailer validation.
+//!
+//! Unlike relying on `flate2`'s zlib wrapper handling to silently accept or reject a stream, this
+//! validates the 2-byte header by hand (FCHECK, CM, CINFO, FDICT) and verifies the big-endian
+//! Adler-32 trailer itself after inflating, giving callers a real [`GzpError`] when either check
+//! fails.
+//!
+//! # References
+//!
+//! - RFC 1950
+
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use bytes::{Buf, Bytes, BytesMut};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+
+use crate::check::{Adler32, Check};
+use crate::GzpError;
+
+/// Validate an RFC 1950 header, returning which field was invalid.
+///
+/// Also reused by [`crate::par::decompress::ParZlibDecompress`] when scanning for stream
+/// boundaries, since zlib's 2-byte header carries no block-size field of its own.
+#[inline]
+pub(crate) fn check_header(header: [u8; 2]) -> Result<(), GzpError> {
+    let cmf = header[0];
+    let flg = header[1];
+    if (u16::from(cmf) * 256 + u16::from(flg)) % 31 != 0 {
+        Err(GzpError::InvalidHeader("FCHECK failed"))
+    } else if cmf & 0x0F != 8 {
+        Err(GzpError::InvalidHeader(
+            "Unsupported compression method, CM must be 8",
+        ))
+    } else if cmf >> 4 > 7 {
+        Err(GzpError::InvalidHeader("Invalid window size, CINFO > 7"))
+    } else if flg & 0x20 != 0 {
+        Err(GzpError::InvalidHeader(
+            "Preset dictionaries (FDICT) are not supported",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Build the 2-byte RFC 1950 header for the given compression level, setting the FDICT bit when
+/// `has_dictionary` is true.
+#[inline]
+fn header_bytes(compression_level: Compression, has_dictionary: bool) -> [u8; 2] {
+    let comp_level = compression_level.level();
+    let flevel: u16 = if comp_level >= 9 {
+        3
+    } else if comp_level == 1 {
+        0
+    } else if comp_level >= 6 {
+        1
+    } else {
+        2
+    };
+
+    let mut head: u16 = (0x78 << 8) + (flevel << 6);
+    if has_dictionary {
+        head += 1 << 5; // FDICT
+    }
+    head += 31 - (head % 31);
+    head.to_be_bytes()
+}
+
+/// A synchronous Zlib (RFC 1950) writer: a 2-byte header, a raw DEFLATE body, and a trailing
+/// big-endian Adler-32.
+pub struct ZlibSyncWriter<W>
+where
+    W: Write,
+{
+    compressor: Compress,
+    check: Adler32,
+    compression_level: Compression,
+    /// A preset dictionary installed on the compressor and advertised in the header's FDICT bit
+    /// and Adler-32 trailer, set via [`ZlibSyncWriter::with_dictionary`].
+    dictionary: Option<Bytes>,
+    wrote_header: bool,
+    finished: bool,
+    writer: W,
+}
+
+impl<W> ZlibSyncWriter<W>
+where
+    W: Write,
+{
+    /// Create a new [`ZlibSyncWriter`].
+    pub fn new(writer: W, compression_level: Compression) -> Self {
+        Self {
+            compressor: Compress::new(compression_level, false),
+            check: Adler32::new(),
+            compression_level,
+            dictionary: None,
+            wrote_header: false,
+            finished: false,
+            writer,
+        }
+    }
+
+    /// Create a new [`ZlibSyncWriter`] that installs `dictionary` on the compressor and
+    /// advertises it in the header's FDICT bit and Adler-32 trailer, per RFC 1950.
+    ///
+    /// # Errors
+    /// - [`GzpError::DictionarySize`] if `dictionary` is larger than [`crate::DICT_SIZE`].
+    pub fn with_dictionary(
+        writer: W,
+        compression_level: Compression,
+        dictionary: Bytes,
+    ) -> Result<Self, GzpError> {
+        if dictionary.len() > crate::DICT_SIZE {
+            return Err(GzpError::DictionarySize(dictionary.len(), crate::DICT_SIZE));
+        }
+        let mut compressor = Compress::new(compression_level, false);
+        compressor.set_dictionary(&dictionary)?;
+        Ok(Self {
+            compressor,
+            check: Adler32::new(),
+            compression_level,
+            dictionary: Some(dictionary),
+            wrote_header: false,
+            finished: false,
+            writer,
+        })
+    }
+
+    /// Write the 2-byte header, and when a preset dictionary is set, its 4-byte big-endian
+    /// Adler-32 trailer, the first time this is called.
+    fn write_header(&mut self) -> io::Result<()> {
+        if !self.wrote_header {
+            self.writer
+                .write_all(&header_bytes(self.compression_level, self.dictionary.is_some()))?;
+            if let Some(dictionary) = &self.dictionary {
+                let mut check = Adler32::new();
+                check.update(dictionary);
+                self.writer.write_u32::<BigEndian>(check.sum())?;
+            }
+            self.wrote_header = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W> Write for ZlibSyncWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_header()?;
+        self.check.update(buf);
+        let mut out = Vec::with_capacity(buf.len());
+        self.compressor
+            .compress_vec(buf, &mut out, FlushCompress::None)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, GzpError::from(e)))?;
+        self.writer.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    /// Flush this output stream. The first call finalizes the DEFLATE stream and writes the
+    /// Adler-32 trailer; subsequent calls are a no-op beyond flushing the inner writer.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_header()?;
+        if !self.finished {
+            let mut out = Vec::with_capacity(64);
+            // `compress_vec` only writes into spare capacity, so grow the buffer for as long as
+            // the compressor keeps filling it.
+            loop {
+                self.compressor
+                    .compress_vec(&[], &mut out, FlushCompress::Finish)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, GzpError::from(e)))?;
+                if out.len() < out.capacity() {
+                    break;
+                }
+                out.reserve(out.capacity());
+            }
+            self.writer.write_all(&out)?;
+            self.writer.write_u32::<BigEndian>(self.check.sum())?;
+            self.finished = true;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W> Drop for ZlibSyncWriter<W>
+where
+    W: Write,
+{
+    fn drop(&mut self) {
+        self.flush().unwrap();
+    }
+}
+
+/// A synchronous Zlib (RFC 1950) reader. Validates the header up front and checks the Adler-32
+/// trailer against the inflated bytes before any are returned.
+pub struct ZlibSyncReader<R> {
+    buffer: BytesMut,
+    reader: Option<R>,
+}
+
+impl<R> ZlibSyncReader<R>
+where
+    R: Read,
+{
+    /// Create a new reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            reader: Some(reader),
+        }
+    }
+
+    /// Validate the header, inflate the whole stream, and verify the Adler-32 trailer.
+    fn decode(&mut self) -> io::Result<()> {
+        let mut reader = self.reader.take().expect("ZlibSyncReader already decoded");
+
+        let mut header = [0u8; 2];
+        reader.read_exact(&mut header)?;
+        check_header(header).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut compressed = Vec::new();
+        reader.read_to_end(&mut compressed)?;
+        if compressed.len() < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "missing Adler-32 trailer",
+            ));
+        }
+        let split = compressed.len() - 4;
+        let expected_sum = BigEndian::read_u32(&compressed[split..]);
+
+        let mut decompressor = Decompress::new(false);
+        let mut output = Vec::with_capacity(4 * split + 64);
+        // `decompress_vec` only writes into spare capacity, so grow the buffer for as long as the
+        // inflater keeps filling it.
+        loop {
+            let consumed = decompressor.total_in() as usize;
+            let status = decompressor
+                .decompress_vec(&compressed[consumed..split], &mut output, FlushDecompress::Finish)
+                .map_err(GzpError::from)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if status == flate2::Status::StreamEnd || output.len() < output.capacity() {
+                break;
+            }
+            output.reserve(output.capacity());
+        }
+
+        let mut check = Adler32::new();
+        check.update(&output);
+        if check.sum() != expected_sum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                GzpError::InvalidCheck {
+                    found: check.sum(),
+                    expected: expected_sum,
+                },
+            ));
+        }
+
+        self.buffer = BytesMut::from(&output[..]);
+        Ok(())
+    }
+}
+
+impl<R> Read for ZlibSyncReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.reader.is_some() {
+            self.decode()?;
+        }
+        let n = std::cmp::min(buf.len(), self.buffer.remaining());
+        self.buffer.copy_to_slice(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::{
+        fs::File,
+        io::{BufReader, BufWriter},
+    };
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_simple_zlibsync() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"
+        This is a longer test than normal to come up with a bunch of text.
+        We'll read just a few lines at a time.
+        ";
+
+        // Compress input to output
+        let mut zlib = ZlibSyncWriter::new(out_writer, Compression::new(3));
+        zlib.write_all(input).unwrap();
+        zlib.flush().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = ZlibSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_zlibsync_rejects_bad_header() {
+        let garbage = [0x00u8, 0x00, 0x00, 0x00];
+        let mut gz = ZlibSyncReader::new(&garbage[..]);
+        let mut bytes = vec![];
+        assert!(gz.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_zlibsync_with_dictionary_sets_fdict_header() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let record = b"2026-07-27T00:00:00Z INFO request completed in 12ms\n";
+        let input = record.repeat(8);
+        let dictionary = Bytes::from_static(record);
+
+        let mut zlib =
+            ZlibSyncWriter::with_dictionary(out_writer, Compression::new(3), dictionary.clone()).unwrap();
+        zlib.write_all(&input).unwrap();
+        zlib.flush().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // FDICT (bit 5 of FLG) is set, and the dictionary's Adler-32 immediately follows the
+        // 2-byte header.
+        assert_eq!(result[1] & 0x20, 0x20);
+        let mut expected_check = Adler32::new();
+        expected_check.update(&dictionary);
+        assert_eq!(BigEndian::read_u32(&result[2..6]), expected_check.sum());
+
+        // `ZlibSyncReader` can't supply the dictionary, so it correctly refuses to read this
+        // stream back.
+        let mut zlib_reader = ZlibSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        assert!(zlib_reader.read_to_end(&mut bytes).is_err());
+    }
+}
@@ -0,0 +1,641 @@
+//! Zstd compression format.
+//!
+//! Each block is compressed as an independent zstd frame, mirroring [`crate::mgzip`], so blocks
+//! can be produced and consumed in parallel instead of needing to share compressor state across
+//! the whole stream. Each block is prefixed with a small fixed-size header recording the total
+//! block size and suffixed with a footer recording the uncompressed size, used to size the output
+//! buffer on decompression.
+//!
+//! The compression level accepted here isn't limited to deflate's conventional 0-9: any
+//! [`Compression`] up to zstd's own maximum of 22 reaches further into zstd's range, clamped at
+//! both ends (see [`zstd_level`]).
+//!
+//! Dictionary training is [`train_dictionary`] (or [`ParCompressBuilder<Zstd>::train_dictionary`]
+//! for the common case of training from a builder's own sample blocks and wiring the result
+//! straight back in); [`Zstd::with_dictionary`] primes every worker's [`zstd::bulk::Compressor`]
+//! with the result via `create_compressor`, separately from [`FormatSpec::needs_dict`] (which
+//! stays `false` here -- that hook is for deflate's rolling cross-block dictionary, not zstd's own
+//! preset-dictionary mechanism). This deliberately goes through zstd's own trained-dictionary
+//! machinery (`Compressor`/`Decompressor::with_dictionary`) rather than the raw-prefix API deflate
+//! uses, since a trained dictionary is the better fit for many small, similarly-shaped records,
+//! which is the case per-block framing hurts most.
+//!
+//! # References
+//!
+//! - [zstd-rs](https://docs.rs/zstd)
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "zstd")] {
+//! use std::{env, fs::File, io::Write};
+//!
+//! use gzp::{zstd::Zstd, par::compress::{ParCompressBuilder, ParCompress}, ZWriter};
+//!
+//! let mut writer = vec![];
+//! let mut parz: ParCompress<Zstd> = ParCompressBuilder::new().from_writer(writer);
+//! parz.write_all(b"This is a first test line\n").unwrap();
+//! parz.write_all(b"This is a second test line\n").unwrap();
+//! parz.finish().unwrap();
+//! # }
+//! ```
+
+use std::io::{self, Read, Write};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use bytes::{Buf, Bytes, BytesMut};
+use flate2::Compression;
+
+use crate::check::PassThroughCheck;
+use crate::par::compress::ParCompressBuilder;
+use crate::syncz::SyncZ;
+use crate::{BlockFormatSpec, FooterValues, FormatSpec, GzpError, SyncWriter, ZWriter, BUFSIZE};
+
+/// Magic bytes identifying a zstd block header.
+pub(crate) const MAGIC: [u8; 4] = *b"ZSTB";
+/// 4 magic bytes + a 4-byte LE total block size.
+const HEADER_SIZE: usize = 8;
+/// 4-byte LE check sum (unused, always 0) + 4-byte LE uncompressed size.
+const FOOTER_SIZE: usize = 8;
+
+/// zstd's supported compression level range, wider than deflate's conventional 0-9.
+const ZSTD_MIN_LEVEL: i32 = 1;
+const ZSTD_MAX_LEVEL: i32 = 22;
+
+/// Map a [`Compression`] level onto zstd's `1..=22` range, clamping rather than erroring on a
+/// value outside it: callers can pass any `Compression::new(level)` up to 22 to reach for a
+/// higher zstd compression level than deflate-based formats support, and a level below 1 (e.g.
+/// `Compression::none()`) is bumped up to zstd's minimum instead of being rejected.
+#[inline]
+fn zstd_level(compression_level: Compression) -> i32 {
+    (compression_level.level() as i32).clamp(ZSTD_MIN_LEVEL, ZSTD_MAX_LEVEL)
+}
+
+/// Train a zstd dictionary from `samples` (e.g. representative records from the data to be
+/// compressed) using zstd's own trainer, returning a buffer usable directly with
+/// [`Zstd::with_dictionary`]. [`ParCompressBuilder::dictionary`](crate::par::compress::ParCompressBuilder::dictionary)
+/// does not reach zstd: [`Zstd`]'s `encode` ignores the `dict` it is handed.
+///
+/// This is the free-standing form of [`ParCompressBuilder<Zstd>::train_dictionary`], for callers
+/// who want the trained bytes without committing to a `Zstd`-specific builder up front.
+pub fn train_dictionary(samples: &[&[u8]], max_dict_size: usize) -> Result<Vec<u8>, GzpError> {
+    Ok(zstd::dict::from_samples(samples, max_dict_size)?)
+}
+
+/// Produce an independent zstd frame per block.
+#[derive(Copy, Clone, Debug)]
+pub struct Zstd {
+    /// A dictionary trained via [`ParCompressBuilder::train_dictionary`], shared by every
+    /// compressor and decompressor thread created from this format instance.
+    ///
+    /// Leaked for the life of the process so `Zstd` can stay `Copy`, as required by
+    /// [`FormatSpec`].
+    dictionary: Option<&'static [u8]>,
+}
+
+impl Zstd {
+    /// Create a [`Zstd`] format bound to an already-trained dictionary, e.g. one loaded back
+    /// from disk to match a dictionary used on the compression side.
+    pub fn with_dictionary(dictionary: Vec<u8>) -> Self {
+        Self {
+            dictionary: Some(Box::leak(dictionary.into_boxed_slice())),
+        }
+    }
+}
+
+impl ParCompressBuilder<Zstd> {
+    /// Train a zstd dictionary from `samples` (e.g. the first few chunks a caller plans to
+    /// write) and use it for every compressor thread this builder spawns.
+    ///
+    /// Per-block parallel compression otherwise loses cross-block redundancy, since each block
+    /// is an independent zstd frame; a shared dictionary built from representative samples wins
+    /// much of that ratio back, which matters most on collections of many small, similar
+    /// records.
+    ///
+    /// Returns the builder along with the trained dictionary bytes, which should be persisted
+    /// (e.g. alongside the compressed output) so a matching [`ParDecompress`](crate::par::decompress::ParDecompress)
+    /// can load it back via [`Zstd::with_dictionary`] and [`ParDecompressBuilder::format`](crate::par::decompress::ParDecompressBuilder::format).
+    pub fn train_dictionary(self, samples: &[Vec<u8>], max_size: usize) -> Result<(Self, Vec<u8>), GzpError> {
+        let samples: Vec<&[u8]> = samples.iter().map(|s| s.as_slice()).collect();
+        let dictionary = train_dictionary(&samples, max_size)?;
+        Ok((self.format(Zstd::with_dictionary(dictionary.clone())), dictionary))
+    }
+}
+
+/// Create a zstd block header, recording the total block size (header + frame + footer).
+#[inline]
+fn header_inner(compressed_size: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(&MAGIC);
+    header
+        .write_u32::<LittleEndian>(compressed_size + HEADER_SIZE as u32 + FOOTER_SIZE as u32)
+        .unwrap();
+    header
+}
+
+/// Compress a block of bytes into an independent zstd frame, adding a header and footer.
+#[inline]
+pub fn compress(input: &[u8], compression_level: Compression) -> Result<Vec<u8>, GzpError> {
+    let compressed = zstd::stream::encode_all(input, zstd_level(compression_level))?;
+
+    let mut buffer = header_inner(compressed.len() as u32);
+    buffer.extend(compressed);
+    // zstd frames are self-checking, so no separate block-level checksum is kept.
+    buffer.write_u32::<LittleEndian>(0).unwrap();
+    buffer.write_u32::<LittleEndian>(input.len() as u32).unwrap();
+    Ok(buffer)
+}
+
+/// Decompress a single zstd-framed block into `output`.
+#[inline]
+pub fn decompress(input: &[u8], output: &mut [u8], footer_vals: FooterValues) -> Result<(), GzpError> {
+    if footer_vals.amount != 0 {
+        let mut cursor = io::Cursor::new(output);
+        zstd::stream::copy_decode(&input[..input.len() - FOOTER_SIZE], &mut cursor)?;
+    }
+    Ok(())
+}
+
+/// Compress a block of bytes using a reusable, possibly dictionary-primed, compressor, adding a
+/// header and footer.
+#[inline]
+fn compress_with(
+    input: &[u8],
+    compressor: &mut zstd::bulk::Compressor<'static>,
+) -> Result<Vec<u8>, GzpError> {
+    let compressed = compressor.compress(input)?;
+
+    let mut buffer = header_inner(compressed.len() as u32);
+    buffer.extend(compressed);
+    // zstd frames are self-checking, so no separate block-level checksum is kept.
+    buffer.write_u32::<LittleEndian>(0).unwrap();
+    buffer.write_u32::<LittleEndian>(input.len() as u32).unwrap();
+    Ok(buffer)
+}
+
+impl FormatSpec for Zstd {
+    type C = PassThroughCheck;
+    type Compressor = zstd::bulk::Compressor<'static>;
+
+    fn new() -> Self {
+        Self { dictionary: None }
+    }
+
+    #[inline]
+    fn needs_dict(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn create_compressor(&self, compression_level: Compression) -> Result<Self::Compressor, GzpError> {
+        let level = zstd_level(compression_level);
+        let compressor = match self.dictionary {
+            Some(dict) => zstd::bulk::Compressor::with_dictionary(level, dict)?,
+            None => zstd::bulk::Compressor::new(level)?,
+        };
+        Ok(compressor)
+    }
+
+    #[inline]
+    fn encode(
+        &self,
+        input: &[u8],
+        compressor: &mut Self::Compressor,
+        _compression_level: Compression,
+        _dict: Option<&Bytes>,
+        _is_first: bool,
+        _is_last: bool,
+    ) -> Result<Vec<u8>, GzpError> {
+        compress_with(input, compressor)
+    }
+
+    fn header(&self, _compression_level: Compression) -> Vec<u8> {
+        vec![]
+    }
+
+    fn footer(&self, _check: &Self::C) -> Vec<u8> {
+        vec![]
+    }
+}
+
+impl BlockFormatSpec for Zstd {
+    type B = PassThroughCheck;
+    type Decompressor = zstd::bulk::Decompressor<'static>;
+
+    const HEADER_SIZE: usize = HEADER_SIZE;
+
+    fn create_decompressor(&self) -> Self::Decompressor {
+        match self.dictionary {
+            Some(dict) => zstd::bulk::Decompressor::with_dictionary(dict),
+            None => zstd::bulk::Decompressor::new(),
+        }
+        .expect("failed to initialize zstd decompressor")
+    }
+
+    #[inline]
+    fn decode_block(
+        &self,
+        decoder: &mut Self::Decompressor,
+        input: &[u8],
+        orig_size: usize,
+    ) -> Result<Vec<u8>, GzpError> {
+        if orig_size == 0 {
+            Ok(vec![])
+        } else {
+            Ok(decoder.decompress(input, orig_size)?)
+        }
+    }
+
+    #[inline]
+    fn check_header(&self, bytes: &[u8]) -> Result<(), GzpError> {
+        if bytes[..4] != MAGIC[..] {
+            Err(GzpError::InvalidHeader("Bad zstd block magic"))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn get_block_size(&self, bytes: &[u8]) -> Result<usize, GzpError> {
+        Ok(LittleEndian::read_u32(&bytes[4..]) as usize)
+    }
+}
+
+/// A synchronous implementation of a Zstd block writer.
+///
+/// **NOTE** use [`Zstd`] via [`crate::par::compress::ParCompress`] for a parallel implementation.
+/// **NOTE** this uses an internal buffer already so the passed in writer almost certainly does not
+/// need to be a BufferedWriter.
+pub struct ZstdSyncWriter<W>
+where
+    W: Write,
+{
+    /// The internal buffer to use
+    buffer: BytesMut,
+    /// The size of the blocks to create
+    blocksize: usize,
+    /// The compression level to use
+    compression_level: Compression,
+    /// The inner writer
+    writer: W,
+}
+
+impl<W> ZstdSyncWriter<W>
+where
+    W: Write,
+{
+    /// Create a new [`ZstdSyncWriter`]
+    pub fn new(writer: W, compression_level: Compression) -> Self {
+        Self::with_capacity(writer, compression_level, BUFSIZE)
+    }
+
+    pub fn with_capacity(writer: W, compression_level: Compression, blocksize: usize) -> Self {
+        Self {
+            buffer: BytesMut::with_capacity(BUFSIZE),
+            blocksize,
+            compression_level,
+            writer,
+        }
+    }
+}
+
+impl<W> Write for ZstdSyncWriter<W>
+where
+    W: Write,
+{
+    /// Write a buffer into this writer, returning how many bytes were written.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= self.blocksize {
+            let b = self.buffer.split_to(self.blocksize).freeze();
+            let compressed = compress(&b[..], self.compression_level)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.writer.write_all(&compressed)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Flush this output stream, ensuring all intermediately buffered contents are sent.
+    fn flush(&mut self) -> std::io::Result<()> {
+        let b = self.buffer.split_to(self.buffer.len()).freeze();
+        if !b.is_empty() {
+            let compressed = compress(&b[..], self.compression_level)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.writer.write_all(&compressed)?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W> Drop for ZstdSyncWriter<W>
+where
+    W: Write,
+{
+    fn drop(&mut self) {
+        self.flush().unwrap();
+    }
+}
+
+impl<W> SyncWriter<W> for Zstd
+where
+    W: Write,
+{
+    type OutputWriter = ZstdSyncWriter<W>;
+
+    fn sync_writer(writer: W, compression_level: Compression) -> Self::OutputWriter {
+        ZstdSyncWriter::new(writer, compression_level)
+    }
+}
+
+impl<W: Write> ZWriter for SyncZ<ZstdSyncWriter<W>> {
+    fn finish(&mut self) -> Result<(), GzpError> {
+        self.inner.take().unwrap().flush()?;
+        Ok(())
+    }
+}
+
+/// A synchronous implementation of a Zstd block reader.
+///
+/// **NOTE** this uses an internal buffer already so the passed in reader almost certainly does not
+/// need to be a BufferedReader.
+pub struct ZstdSyncReader<R>
+where
+    R: Read,
+{
+    buffer: BytesMut,
+    compressed_buffer: BytesMut,
+    reader: R,
+    format: Zstd,
+}
+
+impl<R> ZstdSyncReader<R>
+where
+    R: Read,
+{
+    /// Create a new reader.
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(reader, BUFSIZE)
+    }
+
+    /// Create a new reader with a specified capacity
+    pub fn with_capacity(reader: R, blocksize: usize) -> Self {
+        Self {
+            buffer: BytesMut::with_capacity(blocksize),
+            compressed_buffer: BytesMut::with_capacity(blocksize),
+            reader,
+            format: Zstd { dictionary: None },
+        }
+    }
+}
+
+impl<R> Read for ZstdSyncReader<R>
+where
+    R: Read,
+{
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut total_read = 0;
+        loop {
+            let before = self.buffer.remaining();
+            if before > buf.len() - total_read {
+                self.buffer.copy_to_slice(&mut buf[total_read..]);
+            } else if !self.buffer.is_empty() {
+                self.buffer
+                    .copy_to_slice(&mut buf[total_read..total_read + before]);
+            }
+            let after = self.buffer.remaining();
+            total_read += before - after;
+
+            if total_read == buf.len() {
+                break;
+            } else if total_read <= buf.len() {
+                let mut header_buf = vec![0; HEADER_SIZE];
+                if let Ok(()) = self.reader.read_exact(&mut header_buf) {
+                    self.format
+                        .check_header(&header_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let size = self
+                        .format
+                        .get_block_size(&header_buf)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+                    let remaining = size.checked_sub(HEADER_SIZE).filter(|r| *r >= FOOTER_SIZE).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            GzpError::InvalidBlockSize(
+                                "zstd block size is too small for its own header and footer",
+                            ),
+                        )
+                    })?;
+
+                    self.compressed_buffer.clear();
+                    self.compressed_buffer.resize(remaining, 0);
+                    self.reader.read_exact(&mut self.compressed_buffer)?;
+
+                    let check = self.format.get_footer_values(&self.compressed_buffer);
+                    self.buffer.clear();
+                    self.buffer.resize(check.amount as usize, 0);
+
+                    decompress(&self.compressed_buffer, &mut self.buffer, check)
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        Ok(total_read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::{
+        fs::File,
+        io::{BufReader, BufWriter},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::par::compress::{ParCompress, ParCompressBuilder};
+    use crate::par::decompress::ParDecompressBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_simple_zstdsync() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"
+        This is a longer test than normal to come up with a bunch of text.
+        We'll read just a few lines at a time.
+        ";
+
+        // Compress input to output
+        let mut zstd = ZstdSyncWriter::new(out_writer, Compression::new(3));
+        zstd.write_all(input).unwrap();
+        zstd.flush().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = ZstdSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_par_compress_zstd() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Define input bytes
+        let input = b"
+        This is a longer test than normal to come up with a bunch of text.
+        We'll read just a few lines at a time.
+        ";
+
+        // Compress input to output
+        let mut par_zstd: ParCompress<Zstd> = ParCompressBuilder::new().from_writer(out_writer);
+        par_zstd.write_all(input).unwrap();
+        par_zstd.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = ZstdSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        // Assert decompressed output is equal to input
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_par_compress_zstd_above_deflate_level_range() {
+        let dir = tempdir().unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        let input = b"This is a longer test than normal to come up with a bunch of text.";
+
+        // A level past deflate's 0-9 range should reach further into zstd's own 1-22 range
+        // instead of being misinterpreted or rejected.
+        let mut par_zstd: ParCompress<Zstd> = ParCompressBuilder::new()
+            .compression_level(Compression::new(19))
+            .from_writer(out_writer);
+        par_zstd.write_all(input).unwrap();
+        par_zstd.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress it
+        let mut gz = ZstdSyncReader::new(&result[..]);
+        let mut bytes = vec![];
+        gz.read_to_end(&mut bytes).unwrap();
+
+        assert_eq!(input.to_vec(), bytes);
+    }
+
+    #[test]
+    fn test_par_compress_zstd_with_trained_dictionary() {
+        let dir = tempdir().unwrap();
+
+        // Train a dictionary on samples representative of the many small, similar records this
+        // is meant to help, then use it for every compressor thread.
+        let samples: Vec<Vec<u8>> = (0..50)
+            .map(|i| format!("record {} has a bunch of shared boilerplate text\n", i).into_bytes())
+            .collect();
+        let (builder, dictionary) = ParCompressBuilder::<Zstd>::new()
+            .train_dictionary(&samples, 4096)
+            .unwrap();
+
+        // Create output file
+        let output_file = dir.path().join("output.txt");
+        let out_writer = BufWriter::new(File::create(&output_file).unwrap());
+
+        // Compress input to output using the trained dictionary
+        let mut par_zstd: ParCompress<Zstd> = builder.from_writer(out_writer);
+        for sample in &samples {
+            par_zstd.write_all(sample).unwrap();
+        }
+        par_zstd.finish().unwrap();
+
+        // Read output back in
+        let mut reader = BufReader::new(File::open(output_file).unwrap());
+        let mut result = vec![];
+        reader.read_to_end(&mut result).unwrap();
+
+        // Decompress in parallel, loading the same trained dictionary back
+        let format = Zstd::with_dictionary(dictionary);
+        let mut par_d = ParDecompressBuilder::<Zstd>::new()
+            .format(format)
+            .from_reader(&result[..]);
+        let mut bytes = vec![];
+        par_d.read_to_end(&mut bytes).unwrap();
+
+        assert_eq!(samples.concat(), bytes);
+    }
+
+    #[test]
+    fn test_zstdsync_rejects_garbage_header() {
+        let garbage = [0x00u8; 32];
+        let mut decoder = ZstdSyncReader::new(&garbage[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_zstdsync_rejects_truncated_block() {
+        let mut out_buf = vec![];
+        let mut zstd = ZstdSyncWriter::new(&mut out_buf, Compression::new(3));
+        zstd.write_all(b"hello, world").unwrap();
+        zstd.flush().unwrap();
+        drop(zstd);
+
+        // Chop off the last 4 bytes of the 8-byte footer so the block's declared size runs past
+        // the data actually available.
+        out_buf.truncate(out_buf.len() - 4);
+
+        let mut decoder = ZstdSyncReader::new(&out_buf[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_zstdsync_rejects_undersized_block() {
+        // A header whose declared size leaves no room for even the footer must be rejected
+        // before the `size - HEADER_SIZE` subtraction underflows.
+        let mut header = MAGIC.to_vec();
+        header
+            .write_u32::<LittleEndian>(HEADER_SIZE as u32)
+            .unwrap();
+
+        let mut decoder = ZstdSyncReader::new(&header[..]);
+        let mut bytes = vec![];
+        assert!(decoder.read_to_end(&mut bytes).is_err());
+    }
+}